@@ -0,0 +1,164 @@
+// Batch speed tests for --all, --region, --provider and --servers.
+// Picks the servers to test and aggregates one result per server into a report with the
+// best, worst and median speed.
+
+use crate::downloader::DownloadResult;
+use crate::servers::{self, ServerMetadata};
+use crate::uploader::UploadResult;
+use serde::Serialize;
+
+// Which servers a batch run covers
+pub enum Selector {
+    All,
+    // Region and/or provider, compared without case; both must match when both are given
+    Filter { region: Option<String>, provider: Option<String> },
+    // Names or URLs, tested in the order given
+    Named(Vec<String>),
+}
+
+impl Selector {
+    // Servers from `candidates` (the automatic-selection list) matching the selector. Named
+    // servers are looked up in `full`, so a quarantined server can still be tested on request.
+    pub fn select(&self, candidates: &[ServerMetadata], full: &[ServerMetadata]) -> Result<Vec<ServerMetadata>, String> {
+        let matches = |value: &Option<String>, filter: &Option<String>| match filter {
+            Some(filter) => value.as_ref().is_some_and(|v| v.eq_ignore_ascii_case(filter)),
+            None => true,
+        };
+
+        match self {
+            Selector::All => Ok(candidates.to_vec()),
+            Selector::Filter { region, provider } => Ok(candidates
+                .iter()
+                .filter(|s| matches(&s.region, region) && matches(&s.provider, provider))
+                .cloned()
+                .collect()),
+            Selector::Named(names) => names
+                .iter()
+                .map(|name| servers::find_server(full, name).cloned())
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchEntry {
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    // None when the test failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_mbps: Option<f64>,
+    // Average idle round trip, when the latency probe ran
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    pub bytes: u64,
+    pub total_time: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BatchEntry {
+    fn new(server: &ServerMetadata) -> Self {
+        Self {
+            name: server.name.clone(),
+            url: server.url.clone(),
+            provider: server.provider.clone(),
+            location: server.location.clone(),
+            speed_mbps: None,
+            latency_ms: None,
+            bytes: 0,
+            total_time: 0.0,
+            status_code: None,
+            error: None,
+        }
+    }
+
+    pub fn from_download(server: &ServerMetadata, result: &DownloadResult) -> Self {
+        let success = (result.status_code == 200 || result.status_code == 206) && result.bytes_downloaded > 0;
+        Self {
+            speed_mbps: success.then(|| result.bytes_per_second() * 8.0 / 1_000_000.0),
+            latency_ms: result.latency.and_then(|l| l.idle).map(|idle| idle.avg),
+            bytes: result.bytes_downloaded,
+            total_time: result.total_time,
+            status_code: Some(result.status_code),
+            error: (!success).then(|| format!("HTTP {}", result.status_code)),
+            ..Self::new(server)
+        }
+    }
+
+    pub fn from_upload(server: &ServerMetadata, result: &UploadResult) -> Self {
+        let success = (200..300).contains(&result.status_code);
+        Self {
+            speed_mbps: success.then(|| result.bytes_per_second() * 8.0 / 1_000_000.0),
+            bytes: result.bytes_uploaded,
+            total_time: result.total_time,
+            status_code: Some(result.status_code),
+            error: (!success).then(|| format!("HTTP {}", result.status_code)),
+            ..Self::new(server)
+        }
+    }
+
+    pub fn failed(server: &ServerMetadata, error: &dyn std::error::Error) -> Self {
+        Self {
+            error: Some(error.to_string()),
+            ..Self::new(server)
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchReport {
+    // "download" or "upload"
+    pub direction: &'static str,
+    // How many servers were tested at once; 1 for a sequential run
+    pub parallel: usize,
+    // Seconds from the first test starting to the last one finishing
+    pub wall_time: f64,
+    pub entries: Vec<BatchEntry>,
+}
+
+impl BatchReport {
+    // Successful entries, fastest first
+    pub fn ranked(&self) -> Vec<&BatchEntry> {
+        let mut ranked: Vec<&BatchEntry> = self.entries.iter().filter(|e| e.speed_mbps.is_some()).collect();
+        ranked.sort_by(|a, b| b.speed_mbps.partial_cmp(&a.speed_mbps).unwrap_or(std::cmp::Ordering::Equal));
+        ranked
+    }
+
+    pub fn best(&self) -> Option<&BatchEntry> {
+        self.ranked().first().copied()
+    }
+
+    pub fn worst(&self) -> Option<&BatchEntry> {
+        self.ranked().last().copied()
+    }
+
+    // Median speed of the successful tests; the mean of the middle two for an even count
+    pub fn median_mbps(&self) -> Option<f64> {
+        let speeds: Vec<f64> = self.ranked().iter().filter_map(|e| e.speed_mbps).collect();
+        match speeds.len() {
+            0 => None,
+            n if n % 2 == 1 => Some(speeds[n / 2]),
+            n => Some((speeds[n / 2 - 1] + speeds[n / 2]) / 2.0),
+        }
+    }
+
+    // Everything transferred over the run's wall-clock time. Only meaningful when tests
+    // overlapped, where it approximates the link's total capacity.
+    pub fn aggregate_mbps(&self) -> Option<f64> {
+        if self.parallel <= 1 || self.wall_time <= 0.0 {
+            return None;
+        }
+        let bytes: u64 = self.entries.iter().filter(|e| e.speed_mbps.is_some()).map(|e| e.bytes).sum();
+        (bytes > 0).then(|| bytes as f64 * 8.0 / 1_000_000.0 / self.wall_time)
+    }
+
+    pub fn failures(&self) -> usize {
+        self.entries.iter().filter(|e| e.speed_mbps.is_none()).count()
+    }
+}
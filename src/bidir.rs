@@ -0,0 +1,129 @@
+// Bidirectional speed testing.
+// Runs a download and an upload against one server, either back to back or at the same
+// time, and compares the two directions to flag asymmetric links.
+
+use clap::ValueEnum;
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::time::Duration;
+use crate::config::SpeedUnit;
+use crate::downloader::{download_speed_test, DownloadOptions, DownloadResult};
+use crate::latency::{self, Latency};
+use crate::servers::ServerMetadata;
+use crate::uploader::{upload_data, UploadOptions, UploadResult};
+
+// A link is reported as asymmetric when one direction is at least this many times faster
+const ASYMMETRY_THRESHOLD: f64 = 2.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BidirMode {
+    /// Download first, then upload
+    Sequential,
+    /// Download and upload at the same time
+    Simultaneous,
+}
+
+impl BidirMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BidirMode::Sequential => "sequential",
+            BidirMode::Simultaneous => "simultaneous",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Asymmetry {
+    // Faster direction divided by the slower one
+    pub ratio: f64,
+    pub asymmetric: bool,
+    pub faster: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct BidirResult {
+    pub mode: BidirMode,
+    pub download: DownloadResult,
+    pub upload: UploadResult,
+}
+
+impl BidirResult {
+    pub fn asymmetry(&self) -> Asymmetry {
+        let down = self.download.bytes_per_second();
+        let up = self.upload.bytes_per_second();
+        let (fast, slow, faster) = if down >= up {
+            (down, up, "download")
+        } else {
+            (up, down, "upload")
+        };
+        let ratio = if slow > 0.0 { fast / slow } else { f64::INFINITY };
+
+        Asymmetry {
+            ratio,
+            asymmetric: ratio >= ASYMMETRY_THRESHOLD,
+            faster,
+        }
+    }
+}
+
+pub async fn run_bidir(
+    server: &ServerMetadata,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    mode: BidirMode,
+    download_options: DownloadOptions,
+    upload_options: UploadOptions,
+    show_progress: bool,
+) -> Result<BidirResult, Box<dyn std::error::Error>> {
+    let upload_url = server
+        .upload_url
+        .as_deref()
+        .ok_or_else(|| format!("{} does not accept uploads", server.name))?;
+    let method = server.upload_method();
+
+    let (download, upload) = match mode {
+        BidirMode::Sequential => {
+            let download = download_speed_test(&server.url, user_agent, speed_unit, download_options, show_progress).await?;
+            let upload = upload_data(upload_url, method, user_agent, speed_unit, upload_options, show_progress).await?;
+            (download, upload)
+        }
+        BidirMode::Simultaneous => {
+            // Two progress bars would overwrite each other, so show one spinner for both
+            let spinner = if show_progress {
+                let pb = ProgressBar::new_spinner();
+                pb.set_style(ProgressStyle::default_spinner().template("{spinner:.cyan} {msg} {elapsed}").unwrap());
+                pb.set_message("Downloading and uploading");
+                pb.enable_steady_tick(Duration::from_millis(100));
+                pb
+            } else {
+                ProgressBar::hidden()
+            };
+
+            // Probed here rather than by the download leg: its idle baseline would be taken
+            // while the upload already fills the link, and would hold the download back
+            let target = if download_options.latency { latency::probe_target(&server.url).await } else { None };
+            let idle = match target {
+                Some(addr) => latency::measure_idle(addr).await,
+                None => None,
+            };
+            let probe = target.map(latency::LoadedProbe::start);
+            let download_options = DownloadOptions { latency: false, ..download_options };
+
+            let (download, upload) = tokio::join!(
+                download_speed_test(&server.url, user_agent, speed_unit, download_options, false),
+                upload_data(upload_url, method, user_agent, speed_unit, upload_options, false),
+            );
+            let loaded = probe.and_then(|probe| probe.finish());
+            spinner.finish_and_clear();
+
+            let mut download = download?;
+            if target.is_some() {
+                download.latency = Some(Latency { idle, loaded });
+            }
+            (download, upload?)
+        }
+    };
+
+    Ok(BidirResult { mode, download, upload })
+}
@@ -0,0 +1,74 @@
+// Unicode block charts for `speedo history --chart`.
+// Sparklines, horizontal bars and a column chart over time, as plain characters so the
+// caller can colour them.
+
+const SPARK_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const BAR_BLOCKS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+
+// One block per value, scaled between the smallest and largest value. Longer series are
+// averaged down to `width` blocks.
+pub fn sparkline(values: &[f64], width: usize) -> String {
+    let values = resample(values, width);
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    values
+        .iter()
+        .map(|v| {
+            if max - min <= f64::EPSILON {
+                SPARK_BLOCKS[3]
+            } else {
+                SPARK_BLOCKS[((v - min) / (max - min) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+fn resample(values: &[f64], width: usize) -> Vec<f64> {
+    if values.len() <= width || width == 0 {
+        return values.to_vec();
+    }
+    (0..width)
+        .map(|i| {
+            let chunk = &values[i * values.len() / width..(i + 1) * values.len() / width];
+            chunk.iter().sum::<f64>() / chunk.len() as f64
+        })
+        .collect()
+}
+
+// A horizontal bar `width` cells long at `max`, padded with spaces, in eighths of a cell
+pub fn bar(value: f64, max: f64, width: usize) -> String {
+    let eighths = if max > 0.0 { (value / max * (width * 8) as f64).round() as usize } else { 0 };
+    let eighths = eighths.min(width * 8);
+    let mut bar = "█".repeat(eighths / 8);
+    if eighths % 8 > 0 {
+        bar.push(BAR_BLOCKS[eighths % 8 - 1]);
+    }
+    let used = eighths.div_ceil(8);
+    bar + &" ".repeat(width - used)
+}
+
+// Rows of a column chart, top first: one column per entry, `height` rows tall, scaled from
+// zero to `max`. Empty columns stay blank; any non-zero value shows at least a sliver.
+pub fn columns(values: &[Option<f64>], max: f64, height: usize) -> Vec<Vec<char>> {
+    let levels: Vec<usize> = values
+        .iter()
+        .map(|value| match value {
+            Some(v) if *v > 0.0 && max > 0.0 => ((v / max * (height * 8) as f64).round() as usize).clamp(1, height * 8),
+            _ => 0,
+        })
+        .collect();
+
+    (0..height)
+        .map(|row| {
+            let floor = (height - 1 - row) * 8;
+            levels
+                .iter()
+                .map(|level| match level.saturating_sub(floor).min(8) {
+                    0 => ' ',
+                    n => SPARK_BLOCKS[n - 1],
+                })
+                .collect()
+        })
+        .collect()
+}
@@ -3,6 +3,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct Config {
@@ -14,6 +15,33 @@ pub struct Config {
     pub interactive: bool,
     #[serde(default = "default_speed_unit")]
     pub speed_unit: String,
+    // How long an --auto server choice is reused before probing again (e.g. "24h")
+    #[serde(default = "default_auto_select_ttl")]
+    pub auto_select_ttl: String,
+    // Home location for nearest-server ranking
+    #[serde(default)]
+    pub home_lat: Option<f64>,
+    #[serde(default)]
+    pub home_lon: Option<f64>,
+    // Failures in a row before a server is quarantined (0 disables quarantine)
+    #[serde(default = "default_quarantine_after_failures")]
+    pub quarantine_after_failures: u32,
+    // How long a quarantined server is skipped before it is probed again (e.g. "6h")
+    #[serde(default = "default_quarantine_cooldown")]
+    pub quarantine_cooldown: String,
+    // Servers whose success rate falls below this are listed after the others
+    #[serde(default = "default_demote_below_success_rate")]
+    pub demote_below_success_rate: f64,
+    // Where server lists come from, highest precedence first: "default" (the signed public
+    // list), HTTP(S) URLs, and local JSON/TOML files or directories of them
+    #[serde(default = "default_server_sources")]
+    pub server_sources: Vec<String>,
+    // How long fetched server lists are used before checking for changes (e.g. "7d")
+    #[serde(default = "default_server_list_ttl")]
+    pub server_list_ttl: String,
+    // Append every test run to the local history file
+    #[serde(default = "default_history")]
+    pub history: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -34,12 +62,79 @@ impl SpeedUnit {
             _ => SpeedUnit::BytesMetric, // Default to MB/s
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpeedUnit::BitsMetric => "bits-metric",
+            SpeedUnit::BitsBinary => "bits-binary",
+            SpeedUnit::BytesMetric => "bytes-metric",
+            SpeedUnit::BytesBinary => "bytes-binary",
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+// Parses durations like "10s", "500ms", "2m", "1h" or "30d". A bare number is seconds.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let split = s.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let value: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}'", s))?;
+
+    let seconds = match unit.trim() {
+        "" | "s" | "sec" | "secs" => value,
+        "ms" => value / 1000.0,
+        "m" | "min" | "mins" => value * 60.0,
+        "h" | "hr" | "hrs" => value * 3600.0,
+        "d" | "day" | "days" => value * 86400.0,
+        other => return Err(format!("unknown duration unit '{}' (use ms, s, m, h or d)", other)),
+    };
+
+    Duration::try_from_secs_f64(seconds).map_err(|_| format!("duration '{}' is too long", s))
+}
+
+// A test length for --duration, which has to be above zero
+pub fn parse_test_duration(s: &str) -> Result<Duration, String> {
+    let duration = parse_duration(s)?;
+    if duration.is_zero() {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(duration)
+}
+
+// Parses sizes like "25MB", "100MiB" or "1GB" into bytes
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let size: bytesize::ByteSize = s.trim().parse().map_err(|_| format!("invalid size '{}'", s))?;
+    if size.as_u64() == 0 {
+        return Err("size must be greater than zero".to_string());
+    }
+    Ok(size.as_u64())
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
 pub struct CustomServer {
     pub name: String,
     pub url: String,
+    // Shown in the menu and used for grouping; provider defaults to "Custom"
+    #[serde(default)]
+    pub provider: Option<String>,
+    #[serde(default)]
+    pub location: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    // Coordinates for --nearest
+    #[serde(default)]
+    pub lat: Option<f64>,
+    #[serde(default)]
+    pub lon: Option<f64>,
+    #[serde(default)]
+    pub file_size: Option<u64>,
+    // Set to make the server available for upload tests
+    #[serde(default)]
+    pub upload_url: Option<String>,
+    #[serde(default)]
+    pub upload_method: Option<String>,
 }
 
 fn default_user_agent() -> String {
@@ -50,6 +145,34 @@ fn default_speed_unit() -> String {
     "bytes-metric".to_string()
 }
 
+fn default_auto_select_ttl() -> String {
+    "24h".to_string()
+}
+
+fn default_quarantine_after_failures() -> u32 {
+    3
+}
+
+fn default_quarantine_cooldown() -> String {
+    "6h".to_string()
+}
+
+fn default_demote_below_success_rate() -> f64 {
+    0.5
+}
+
+fn default_server_list_ttl() -> String {
+    "7d".to_string()
+}
+
+fn default_history() -> bool {
+    true
+}
+
+fn default_server_sources() -> Vec<String> {
+    vec![crate::sources::DEFAULT_SOURCE.to_string()]
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -57,6 +180,15 @@ impl Default for Config {
             custom_servers: Vec::new(),
             interactive: false,
             speed_unit: default_speed_unit(),
+            auto_select_ttl: default_auto_select_ttl(),
+            home_lat: None,
+            home_lon: None,
+            quarantine_after_failures: default_quarantine_after_failures(),
+            quarantine_cooldown: default_quarantine_cooldown(),
+            demote_below_success_rate: default_demote_below_success_rate(),
+            server_sources: default_server_sources(),
+            server_list_ttl: default_server_list_ttl(),
+            history: default_history(),
         }
     }
 }
@@ -83,7 +215,8 @@ pub fn load_config() -> Config {
         if path.exists() {
             if let Ok(contents) = std::fs::read_to_string(&path) {
                 if let Ok(config) = toml::from_str::<Config>(&contents) {
-                    println!("{}", format!("Loaded config from: {}", path.display()).bright_black());
+                    // stderr, so JSON and CSV on stdout stay machine-readable
+                    eprintln!("{}", format!("Loaded config from: {}", path.display()).bright_black());
                     return config;
                 }
             }
@@ -91,3 +224,54 @@ pub fn load_config() -> Config {
     }
     Config::default()
 }
+
+// The config file that is read, or ~/.speedo.toml when there is none yet
+pub fn config_file_path() -> PathBuf {
+    let paths = get_config_paths();
+    paths.iter()
+        .find(|p| p.exists())
+        .or_else(|| paths.last())
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("speedo.toml"))
+}
+
+// Edits the config file in place with toml_edit, keeping the user's comments and layout
+fn edit_config_file(edit: impl FnOnce(&mut toml_edit::DocumentMut) -> Result<(), Box<dyn std::error::Error>>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let path = config_file_path();
+    let contents = if path.exists() {
+        std::fs::read_to_string(&path)?
+    } else {
+        String::new()
+    };
+    let mut document: toml_edit::DocumentMut = contents.parse()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    
+    edit(&mut document)?;
+    std::fs::write(&path, document.to_string())?;
+    Ok(path)
+}
+
+fn custom_server_tables(document: &mut toml_edit::DocumentMut) -> Result<&mut toml_edit::ArrayOfTables, Box<dyn std::error::Error>> {
+    document
+        .entry("custom_servers")
+        .or_insert(toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()))
+        .as_array_of_tables_mut()
+        .ok_or_else(|| "custom_servers must be written as [[custom_servers]] tables to be edited".into())
+}
+
+// Appends a [[custom_servers]] entry, returning the file that was written
+pub fn add_custom_server(server: &CustomServer) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let table = toml_edit::ser::to_document(server)?.as_table().clone();
+    edit_config_file(|document| {
+        custom_server_tables(document)?.push(table);
+        Ok(())
+    })
+}
+
+// Removes every [[custom_servers]] entry with this URL, returning the file that was written
+pub fn remove_custom_server(url: &str) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    edit_config_file(|document| {
+        custom_server_tables(document)?.retain(|table| table.get("url").and_then(|u| u.as_str()) != Some(url));
+        Ok(())
+    })
+}
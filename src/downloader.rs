@@ -2,16 +2,21 @@
 // Downloads files from URLs with optional save-to-disk, reporting connection time,
 // TTFB, total time, and bytes downloaded.
 
-use indicatif::{ProgressBar, ProgressStyle};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use reqwest::Client;
-use serde::Serialize;
-use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::fs::File;
 use tokio::io::AsyncWriteExt;
 use crate::config::SpeedUnit;
+use crate::latency::{self, Latency};
+use crate::servers::HealthSample;
+use crate::timing::{timed_client, PhaseTimings};
 use bytesize::ByteSize;
 
-fn format_speed(bytes_per_sec: f64, unit: SpeedUnit) -> String {
+pub fn format_speed(bytes_per_sec: f64, unit: SpeedUnit) -> String {
     match unit {
         SpeedUnit::BitsMetric => {
             let bits_per_sec = bytes_per_sec * 8.0;
@@ -49,10 +54,248 @@ fn format_speed(bytes_per_sec: f64, unit: SpeedUnit) -> String {
 #[derive(Debug, Clone, Serialize)]
 pub struct DownloadResult {
     pub status_code: u16,
+    // Seconds from start until the connection was ready (DNS + TCP + TLS)
     pub connect_time: f64,
+    // Seconds from start until the response headers arrived
     pub ttfb: f64,
     pub total_time: f64,
     pub bytes_downloaded: u64,
+    pub timings: PhaseTimings,
+    // Per-connection breakdown, only populated in multi-connection or segmented mode
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub streams: Vec<StreamResult>,
+    // Byte offset a resumed download continued from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resumed_from: Option<u64>,
+    // Data received during the warm-up window of a duration-bounded test
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<Warmup>,
+    // Whether the transfer was cut off by the duration budget
+    pub time_limited: bool,
+    // Round-trip times to the server before and during the transfer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Latency>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Warmup {
+    pub time: f64,
+    pub bytes: u64,
+}
+
+impl DownloadResult {
+    // Outcome for the server's health record. Latency prefers the idle probe and falls
+    // back to the connect time of a plain HTTP test connection; with TLS it would include
+    // the handshake.
+    pub fn health_sample(&self) -> HealthSample {
+        let success = (self.status_code == 200 || self.status_code == 206) && self.bytes_downloaded > 0;
+        let latency_ms = self.latency
+            .and_then(|l| l.idle)
+            .map(|idle| idle.avg)
+            .or_else(|| (!self.timings.tls && self.timings.connect > 0.0).then_some(self.timings.connect * 1000.0));
+
+        HealthSample {
+            success,
+            speed_mbps: success.then(|| self.bytes_per_second() * 8.0 / 1_000_000.0),
+            latency_ms: if success { latency_ms } else { None },
+        }
+    }
+
+    // Throughput with the warm-up window (if any) excluded, so TCP slow start
+    // does not drag the result down
+    pub fn bytes_per_second(&self) -> f64 {
+        match self.warmup {
+            Some(w) if self.total_time > w.time => {
+                (self.bytes_downloaded - w.bytes) as f64 / (self.total_time - w.time)
+            }
+            _ if self.total_time > 0.0 => self.bytes_downloaded as f64 / self.total_time,
+            _ => 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StreamResult {
+    pub stream: usize,
+    pub status_code: u16,
+    pub connect_time: f64,
+    pub ttfb: f64,
+    pub total_time: f64,
+    pub bytes_downloaded: u64,
+    pub timings: PhaseTimings,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub time_limited: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DownloadOptions {
+    pub connections: usize,
+    // Continue an existing partial file instead of starting over
+    pub resume: bool,
+    // Stop the test after this long instead of waiting for the whole file
+    pub duration: Option<Duration>,
+    // Leading part of a duration-bounded test excluded from the speed calculation
+    pub warmup: Duration,
+    // Probe idle and loaded latency around the transfer
+    pub latency: bool,
+}
+
+impl Default for DownloadOptions {
+    fn default() -> Self {
+        Self {
+            connections: 1,
+            resume: false,
+            duration: None,
+            warmup: Duration::ZERO,
+            latency: false,
+        }
+    }
+}
+
+// Deadlines for a duration-bounded test, measured from the start of the request
+#[derive(Debug, Clone, Copy)]
+struct TransferBudget {
+    deadline: Option<Instant>,
+    warmup_end: Option<Instant>,
+}
+
+impl TransferBudget {
+    fn new(start: Instant, options: &DownloadOptions) -> Self {
+        match options.duration {
+            Some(duration) => TransferBudget {
+                deadline: Some(start + duration),
+                warmup_end: Some(start + options.warmup).filter(|_| !options.warmup.is_zero()),
+            },
+            None => TransferBudget { deadline: None, warmup_end: None },
+        }
+    }
+
+    // Waits for the next chunk, or returns `Err` once the deadline has passed
+    async fn next_chunk<S>(&self, stream: &mut S) -> Result<Option<S::Item>, ()>
+    where
+        S: futures_util::Stream + Unpin,
+    {
+        use futures_util::StreamExt;
+
+        match self.deadline {
+            Some(deadline) => tokio::time::timeout_at(deadline.into(), stream.next())
+                .await
+                .map_err(|_| ()),
+            None => Ok(stream.next().await),
+        }
+    }
+
+    // Requests the file again after its body ended before the deadline, so a fast link
+    // keeps transferring for the whole budget. None once the deadline has passed.
+    async fn refill(&self, request: reqwest::RequestBuilder) -> Result<Option<reqwest::Response>, reqwest::Error> {
+        let Some(deadline) = self.deadline.filter(|deadline| Instant::now() < *deadline) else {
+            return Ok(None);
+        };
+        match tokio::time::timeout_at(deadline.into(), request.send()).await {
+            Ok(response) => Ok(Some(response?.error_for_status()?)),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+// Validators recorded next to a file while it is being saved, so an interrupted
+// download can later be resumed with `If-Range`. Removed once the download completes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ResumeInfo {
+    url: String,
+    #[serde(default)]
+    etag: Option<String>,
+    #[serde(default)]
+    last_modified: Option<String>,
+    // Segmented downloads preallocate the file, so its length says nothing about progress
+    #[serde(default)]
+    segmented: bool,
+}
+
+fn resume_info_path(save_path: &str) -> String {
+    format!("{}.speedo-resume", save_path)
+}
+
+fn load_resume_info(save_path: &str) -> Option<ResumeInfo> {
+    let contents = std::fs::read_to_string(resume_info_path(save_path)).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_resume_info(save_path: &str, info: &ResumeInfo) -> std::io::Result<()> {
+    let json = serde_json::to_string_pretty(info)?;
+    std::fs::write(resume_info_path(save_path), json)
+}
+
+fn remove_resume_info(save_path: &str) {
+    std::fs::remove_file(resume_info_path(save_path)).ok();
+}
+
+impl ResumeInfo {
+    fn from_headers(url: &str, headers: &reqwest::header::HeaderMap, segmented: bool) -> Self {
+        let header = |name| headers.get(name).and_then(|v: &reqwest::header::HeaderValue| v.to_str().ok()).map(|v| v.to_string());
+        ResumeInfo {
+            url: url.to_string(),
+            etag: header(reqwest::header::ETAG),
+            last_modified: header(reqwest::header::LAST_MODIFIED),
+            segmented,
+        }
+    }
+
+    // If-Range only accepts strong ETags, so weak ones fall back to Last-Modified
+    fn if_range(&self) -> Option<&str> {
+        self.etag.as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+// Where a transfer draws its progress bar
+#[derive(Clone)]
+pub enum Progress {
+    Hidden,
+    Standalone,
+    // One bar among several running at once, prefixed with a label such as the server name
+    Multi(MultiProgress, String),
+}
+
+impl From<bool> for Progress {
+    fn from(show: bool) -> Self {
+        if show {
+            Progress::Standalone
+        } else {
+            Progress::Hidden
+        }
+    }
+}
+
+impl Progress {
+    // A bar for `total` bytes, or a spinner when the size is unknown
+    pub fn bar(&self, total: Option<u64>) -> ProgressBar {
+        let template = if total.is_some() {
+            "{bar:40.cyan/blue} {bytes}/{total_bytes} {msg} ({eta})"
+        } else {
+            "{spinner:.cyan} {bytes} {msg}"
+        };
+        let style = |template: &str| {
+            ProgressStyle::default_bar()
+                .template(template)
+                .unwrap()
+                .progress_chars("##-")
+        };
+
+        match self {
+            Progress::Hidden => ProgressBar::hidden(),
+            Progress::Standalone => ProgressBar::new(total.unwrap_or(0)).with_style(style(template)),
+            // Styled before it is added so the bar is never drawn with the default style
+            Progress::Multi(multi, label) => {
+                let template = format!("{{prefix:.bold}} {}", template.replace("bar:40", "bar:30"));
+                let pb = ProgressBar::new(total.unwrap_or(0))
+                    .with_style(style(&template))
+                    .with_prefix(label.clone());
+                multi.add(pb)
+            }
+        }
+    }
 }
 
 pub async fn download_file(
@@ -60,10 +303,72 @@ pub async fn download_file(
     save_path: Option<&str>,
     user_agent: &str,
     speed_unit: SpeedUnit,
+    options: DownloadOptions,
 ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
-    download_file_with_progress(url, save_path, user_agent, speed_unit, true).await
+    // Cutting a saved file short would leave it truncated, so time limits only apply to speed tests
+    let options = if save_path.is_some() {
+        DownloadOptions { duration: None, ..options }
+    } else {
+        options
+    };
+
+    let download = async {
+        match save_path {
+            // Segments cannot be resumed individually, so resuming always uses one stream
+            _ if options.connections <= 1 || options.resume => {
+                download_single(url, save_path, user_agent, speed_unit, Progress::Standalone, options).await
+            }
+            Some(path) => download_segmented(url, path, user_agent, speed_unit, options.connections, true).await,
+            None => download_multi_stream(url, user_agent, speed_unit, options, Progress::Standalone).await,
+        }
+    };
+    // Probing only matters for speed tests; a file download would just be slowed down by it
+    with_latency(url, options.latency && save_path.is_none(), download).await
 }
 
+// Measures idle latency, then keeps probing while `download` runs and attaches both to the result
+async fn with_latency(
+    url: &str,
+    enabled: bool,
+    download: impl std::future::Future<Output = Result<DownloadResult, Box<dyn std::error::Error>>>,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    let target = if enabled { latency::probe_target(url).await } else { None };
+    let Some(addr) = target else {
+        return download.await;
+    };
+
+    let idle = latency::measure_idle(addr).await;
+    let probe = latency::LoadedProbe::start(addr);
+    let result = download.await;
+    let loaded = probe.finish();
+
+    let mut result = result?;
+    result.latency = Some(Latency { idle, loaded });
+    Ok(result)
+}
+
+// Speed test download that discards the body, with the progress bar optional so it can
+// run alongside other tests
+pub async fn download_speed_test(
+    url: &str,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    options: DownloadOptions,
+    progress: impl Into<Progress>,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    let progress = progress.into();
+    let download = async {
+        if options.connections <= 1 {
+            download_single(url, None, user_agent, speed_unit, progress, options).await
+        } else {
+            download_multi_stream(url, user_agent, speed_unit, options, progress).await
+        }
+    };
+    with_latency(url, options.latency, download).await
+}
+
+// Single-stream download used by the GUI
+#[cfg_attr(not(feature = "gui"), allow(dead_code))]
 pub async fn download_file_with_progress(
     url: &str,
     save_path: Option<&str>,
@@ -71,66 +376,161 @@ pub async fn download_file_with_progress(
     speed_unit: SpeedUnit,
     show_progress: bool,
 ) -> Result<DownloadResult, Box<dyn std::error::Error>> {
-    let client = Client::builder()
-        .user_agent(user_agent)
-        .build()?;
+    let download = download_single(url, save_path, user_agent, speed_unit, show_progress.into(), DownloadOptions::default());
+    with_latency(url, true, download).await
+}
+
+async fn download_single(
+    url: &str,
+    save_path: Option<&str>,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    progress: Progress,
+    options: DownloadOptions,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    let (client, recorder) = timed_client(url, user_agent)?;
+
+    // Work out how much of a partial file we already have
+    let mut existing_len = 0u64;
+    let mut request = client.get(url);
+    if let (true, Some(path)) = (options.resume, save_path) {
+        let info = load_resume_info(path);
+        let len = tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0);
+        let resumable = info.as_ref()
+            .map(|i| i.url == url && !i.segmented)
+            .unwrap_or(true);
+
+        if len > 0 && resumable {
+            existing_len = len;
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", len));
+            match info.as_ref().and_then(|i| i.if_range()) {
+                Some(validator) => request = request.header(reqwest::header::IF_RANGE, validator),
+                None => {
+                    use colored::Colorize;
+                    eprintln!(
+                        "{}",
+                        format!("Warning: no ETag or Last-Modified recorded for {}, so speedo cannot check that the file is unchanged before appending", path).yellow()
+                    );
+                }
+            }
+        }
+    }
 
     let start = Instant::now();
+    let budget = TransferBudget::new(start, &options);
     
-    let response = client.get(url).send().await?;
-    let connect_time = start.elapsed().as_secs_f64();
+    let response = request.send().await?;
+    let headers_at = Instant::now();
+    let ttfb = headers_at.duration_since(start).as_secs_f64();
+    let connect_time = recorder.connect_time(start).unwrap_or(ttfb);
     
     let status_code = response.status().as_u16();
-    let total_size = response.content_length().unwrap_or(0);
+
+    // 416 means the range starts at or past the end: the file is already complete
+    if existing_len > 0 && status_code == 416 {
+        if let Some(path) = save_path {
+            remove_resume_info(path);
+        }
+        return Ok(DownloadResult {
+            status_code,
+            connect_time,
+            ttfb,
+            total_time: start.elapsed().as_secs_f64(),
+            bytes_downloaded: 0,
+            timings: recorder.timings(start, headers_at, Instant::now()),
+            streams: Vec::new(),
+            resumed_from: Some(existing_len),
+            warmup: None,
+            time_limited: false,
+            latency: None,
+        });
+    }
+
+    // 206 continues the partial file and 200 is the full body (unsupported ranges or the
+    // file changed since If-Range), so start over. Anything else is an error, and the partial
+    // file is left alone for a later attempt.
+    let resumed_from = match status_code {
+        _ if existing_len == 0 => None,
+        206 => {
+            let content_range = response.headers()
+                .get(reqwest::header::CONTENT_RANGE)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or("");
+            if !content_range.starts_with(&format!("bytes {}-", existing_len)) {
+                return Err(format!(
+                    "server answered the resume request with Content-Range '{}', expected bytes {}-",
+                    content_range, existing_len
+                ).into());
+            }
+            Some(existing_len)
+        }
+        200 => None,
+        _ => return Err(format!("server answered the resume request with HTTP {}; the partial file was kept", status_code).into()),
+    };
+    let offset = resumed_from.unwrap_or(0);
+    let total_size = response.content_length().map(|len| len + offset).unwrap_or(0);
+
+    let mut file: Option<File> = None;
+    if let Some(path) = save_path {
+        save_resume_info(path, &ResumeInfo::from_headers(url, response.headers(), false)).ok();
+        file = Some(if resumed_from.is_some() {
+            tokio::fs::OpenOptions::new().append(true).open(path).await?
+        } else {
+            File::create(path).await?
+        });
+    }
     
-    let ttfb_start = Instant::now();
     let mut stream = response.bytes_stream();
     
-    let pb = if show_progress {
-        let pb = ProgressBar::new(total_size);
-        
-        // Use different template based on whether we know the file size
-        let template = if total_size > 0 {
-            "{bar:40.cyan/blue} {bytes}/{total_bytes} {msg} ({eta})"
-        } else {
-            "{spinner:.cyan} {bytes} {msg}"
-        };
-        
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template(template)
-                .unwrap()
-                .progress_chars("##-"),
-        );
-        pb
-    } else {
-        ProgressBar::hidden()
-    };
+    // Without a known size the bar becomes a spinner
+    let pb = progress.bar((total_size > 0).then_some(total_size));
 
     let mut downloaded: u64 = 0;
-    let mut ttfb: Option<f64> = None;
-    let mut file: Option<File> = None;
     let mut last_update = Instant::now();
     let mut last_downloaded = 0u64;
+    pb.set_position(offset);
 
-    if let Some(path) = save_path {
-        file = Some(File::create(path).await?);
-    }
-
-    use futures_util::StreamExt;
+    let mut warmup: Option<Warmup> = None;
+    let mut time_limited = false;
+    // Only speed tests are refilled; an empty body would just be requested over and over
+    let refill = budget.deadline.is_some() && save_path.is_none();
+    let mut body_bytes: u64 = 0;
     
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        
-        if ttfb.is_none() {
-            ttfb = Some(ttfb_start.elapsed().as_secs_f64());
-        }
+    loop {
+        let chunk = match budget.next_chunk(&mut stream).await {
+            Ok(Some(chunk)) => chunk?,
+            Ok(None) if refill && body_bytes > 0 => match budget.refill(client.get(url)).await? {
+                Some(response) => {
+                    pb.inc_length(response.content_length().unwrap_or(0));
+                    stream = response.bytes_stream();
+                    body_bytes = 0;
+                    continue;
+                }
+                None => {
+                    time_limited = true;
+                    break;
+                }
+            },
+            Ok(None) => break,
+            Err(()) => {
+                time_limited = true;
+                break;
+            }
+        };
         
+        body_bytes += chunk.len() as u64;
         downloaded += chunk.len() as u64;
-        pb.set_position(downloaded);
+        pb.set_position(offset + downloaded);
         
-        // Update speed message every 100ms
         let now = Instant::now();
+        if warmup.is_none() && budget.warmup_end.is_some_and(|end| now >= end) {
+            warmup = Some(Warmup {
+                time: now.duration_since(start).as_secs_f64(),
+                bytes: downloaded,
+            });
+        }
+        
+        // Update speed message every 100ms
         if now.duration_since(last_update).as_millis() >= 100 {
             let elapsed = now.duration_since(last_update).as_secs_f64();
             let bytes_diff = downloaded - last_downloaded;
@@ -147,21 +547,329 @@ pub async fn download_file_with_progress(
 
     pb.finish_and_clear();
 
+    if let (Some(path), Some(f)) = (save_path, file.as_mut()) {
+        f.flush().await?;
+        remove_resume_info(path);
+    }
+
+    let end = Instant::now();
+    let total_time = end.duration_since(start).as_secs_f64();
+
+    Ok(DownloadResult {
+        status_code,
+        connect_time,
+        ttfb,
+        total_time,
+        bytes_downloaded: downloaded,
+        timings: recorder.timings(start, headers_at, end),
+        streams: Vec::new(),
+        resumed_from,
+        warmup,
+        time_limited,
+        latency: None,
+    })
+}
+
+// Opens `connections` concurrent streams against the same URL and aggregates them
+// into a single result. Each stream gets its own client so it lands on its own
+// TCP connection instead of being multiplexed.
+pub async fn download_multi_stream(
+    url: &str,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    options: DownloadOptions,
+    progress: Progress,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    // Every stream adds its length as its response arrives
+    let pb = progress.bar(Some(0));
+    run_streams(url, user_agent, speed_unit, vec![None; options.connections], None, options, pb).await
+}
+
+// Downloads a file in `connections` byte-range segments written at their offsets
+// into a preallocated file. Falls back to a single stream when the server does
+// not advertise `Accept-Ranges: bytes` or a Content-Length.
+pub async fn download_segmented(
+    url: &str,
+    save_path: &str,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    connections: usize,
+    show_progress: bool,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    let client = Client::builder()
+        .user_agent(user_agent)
+        .build()?;
+
+    let head = client.head(url).send().await?;
+    let accepts_ranges = head.headers()
+        .get(reqwest::header::ACCEPT_RANGES)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("bytes"))
+        .unwrap_or(false);
+    let total_size = head.headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+
+    if !head.status().is_success() || !accepts_ranges || total_size == 0 {
+        return download_single(url, Some(save_path), user_agent, speed_unit, show_progress.into(), DownloadOptions::default()).await;
+    }
+
+    // Preallocate so every segment can write at its own offset
+    let file = File::create(save_path).await?;
+    file.set_len(total_size).await?;
+    drop(file);
+    save_resume_info(save_path, &ResumeInfo::from_headers(url, head.headers(), true)).ok();
+
+    let segments = (connections as u64).min(total_size);
+    let segment_size = total_size.div_ceil(segments);
+    let ranges: Vec<Option<(u64, u64)>> = (0..segments)
+        .map(|i| {
+            let first = i * segment_size;
+            let last = ((i + 1) * segment_size).min(total_size) - 1;
+            Some((first, last))
+        })
+        .collect();
+
+    let pb = Progress::from(show_progress).bar(Some(total_size));
+    let result = run_streams(url, user_agent, speed_unit, ranges, Some(save_path), DownloadOptions::default(), pb).await?;
+    remove_resume_info(save_path);
+    Ok(result)
+}
+
+// Runs one stream per entry in `ranges` concurrently and aggregates the results.
+// A `None` range fetches the whole body; `Some((first, last))` fetches that byte
+// range and, when `save_path` is set, writes it at `first` in the file.
+async fn run_streams(
+    url: &str,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    ranges: Vec<Option<(u64, u64)>>,
+    save_path: Option<&str>,
+    options: DownloadOptions,
+    pb: ProgressBar,
+) -> Result<DownloadResult, Box<dyn std::error::Error>> {
+    let start = Instant::now();
+    let budget = TransferBudget::new(start, &options);
+    let received = Arc::new(AtomicU64::new(0));
+
+    let mut handles = Vec::with_capacity(ranges.len());
+    for (stream, range) in ranges.into_iter().enumerate() {
+        let url = url.to_string();
+        let user_agent = user_agent.to_string();
+        let save_path = save_path.map(|p| p.to_string());
+        let shared = SharedTransfer {
+            start,
+            budget,
+            received: received.clone(),
+            pb: pb.clone(),
+        };
+        handles.push(tokio::spawn(async move {
+            download_stream(stream, &url, &user_agent, range, save_path.as_deref(), shared)
+                .await
+                .map_err(|e| e.to_string())
+        }));
+    }
+
+    // Snapshot the combined byte count once the warm-up window closes
+    let warmup = Arc::new(Mutex::new(None::<Warmup>));
+    let warmup_task = budget.warmup_end.map(|warmup_end| {
+        let warmup = warmup.clone();
+        let received = received.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep_until(warmup_end.into()).await;
+            *warmup.lock().unwrap() = Some(Warmup {
+                time: start.elapsed().as_secs_f64(),
+                bytes: received.load(Ordering::Relaxed),
+            });
+        })
+    });
+
+    // Update the aggregate speed message every 100ms while streams run
+    let ticker_pb = pb.clone();
+    let ticker = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        let mut last_update = Instant::now();
+        let mut last_downloaded = 0u64;
+        loop {
+            interval.tick().await;
+            let now = Instant::now();
+            let downloaded = ticker_pb.position();
+            let elapsed = now.duration_since(last_update).as_secs_f64();
+            if elapsed > 0.0 {
+                let speed = (downloaded - last_downloaded) as f64 / elapsed;
+                ticker_pb.set_message(format_speed(speed, speed_unit));
+            }
+            last_update = now;
+            last_downloaded = downloaded;
+        }
+    });
+
+    let mut streams = Vec::with_capacity(handles.len());
+    let mut first_error = None;
+    for handle in handles {
+        match handle.await {
+            Ok(Ok(result)) => streams.push(result),
+            Ok(Err(e)) => { first_error.get_or_insert(e); }
+            Err(e) => { first_error.get_or_insert(e.to_string()); }
+        }
+    }
+
+    ticker.abort();
+    if let Some(task) = warmup_task {
+        task.abort();
+    }
+    pb.finish_and_clear();
+
+    if let Some(e) = first_error {
+        return Err(format!("Stream failed: {}", e).into());
+    }
+
     let total_time = start.elapsed().as_secs_f64();
+    // Partial Content is the expected status for ranged segments, so report it as OK
+    let status_code = streams.iter()
+        .map(|s| s.status_code)
+        .find(|&code| code != 200 && code != 206)
+        .unwrap_or(200);
+    let connect_time = streams.iter().map(|s| s.connect_time).fold(f64::INFINITY, f64::min);
+    let ttfb = streams.iter().map(|s| s.ttfb).fold(f64::INFINITY, f64::min);
+    // Report the phases of the stream that received its first byte soonest
+    let timings = streams.iter()
+        .min_by(|a, b| a.ttfb.total_cmp(&b.ttfb))
+        .map(|s| s.timings)
+        .unwrap_or_default();
+    let warmup = *warmup.lock().unwrap();
 
     Ok(DownloadResult {
         status_code,
         connect_time,
-        ttfb: ttfb.unwrap_or(connect_time),
+        ttfb,
         total_time,
+        timings,
+        bytes_downloaded: streams.iter().map(|s| s.bytes_downloaded).sum(),
+        time_limited: streams.iter().any(|s| s.time_limited),
+        streams,
+        resumed_from: None,
+        warmup,
+        latency: None,
+    })
+}
+
+// State shared by every stream of one multi-stream run
+struct SharedTransfer {
+    start: Instant,
+    budget: TransferBudget,
+    received: Arc<AtomicU64>,
+    pb: ProgressBar,
+}
+
+async fn download_stream(
+    stream: usize,
+    url: &str,
+    user_agent: &str,
+    range: Option<(u64, u64)>,
+    save_path: Option<&str>,
+    shared: SharedTransfer,
+) -> Result<StreamResult, Box<dyn std::error::Error>> {
+    use tokio::io::AsyncSeekExt;
+
+    let SharedTransfer { start, budget, received, pb } = shared;
+
+    let (client, recorder) = timed_client(url, user_agent)?;
+
+    let mut request = client.get(url);
+    if let Some((first, last)) = range {
+        request = request.header(reqwest::header::RANGE, format!("bytes={}-{}", first, last));
+    }
+
+    // Streams share the overall start, so phases are measured from this request's own send
+    let request_start = Instant::now();
+    let response = request.send().await?;
+    let headers_at = Instant::now();
+    let ttfb = headers_at.duration_since(start).as_secs_f64();
+    let connect_time = recorder.connect_time(start).unwrap_or(ttfb);
+
+    let status_code = response.status().as_u16();
+
+    let mut file: Option<File> = None;
+    match range {
+        Some((first, _)) => {
+            // A 200 here means the server ignored the range and is sending the
+            // whole body, which would corrupt the file if written at an offset
+            if status_code != 206 {
+                return Err(format!("server ignored range request (status {})", status_code).into());
+            }
+            if let Some(path) = save_path {
+                let mut f = tokio::fs::OpenOptions::new().write(true).open(path).await?;
+                f.seek(std::io::SeekFrom::Start(first)).await?;
+                file = Some(f);
+            }
+        }
+        None => pb.inc_length(response.content_length().unwrap_or(0)),
+    }
+
+    let mut body = response.bytes_stream();
+    let mut downloaded: u64 = 0;
+    let mut time_limited = false;
+    // Whole-body speed test streams keep fetching the file until the deadline, like download_single
+    let refill = budget.deadline.is_some() && range.is_none() && save_path.is_none();
+    let mut body_bytes: u64 = 0;
+
+    loop {
+        let chunk = match budget.next_chunk(&mut body).await {
+            Ok(Some(chunk)) => chunk?,
+            Ok(None) if refill && body_bytes > 0 => match budget.refill(client.get(url)).await? {
+                Some(response) => {
+                    pb.inc_length(response.content_length().unwrap_or(0));
+                    body = response.bytes_stream();
+                    body_bytes = 0;
+                    continue;
+                }
+                None => {
+                    time_limited = true;
+                    break;
+                }
+            },
+            Ok(None) => break,
+            Err(()) => {
+                time_limited = true;
+                break;
+            }
+        };
+
+        body_bytes += chunk.len() as u64;
+        downloaded += chunk.len() as u64;
+        received.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        pb.inc(chunk.len() as u64);
+
+        if let Some(ref mut f) = file {
+            f.write_all(&chunk).await?;
+        }
+    }
+
+    if let Some(ref mut f) = file {
+        f.flush().await?;
+    }
+
+    let end = Instant::now();
+
+    Ok(StreamResult {
+        stream,
+        status_code,
+        connect_time,
+        ttfb,
+        total_time: end.duration_since(start).as_secs_f64(),
         bytes_downloaded: downloaded,
+        timings: recorder.timings(request_start, headers_at, end),
+        time_limited,
     })
 }
 
 pub fn extract_filename(url: &str) -> String {
     url.split('?')
         .next()
-        .and_then(|s| s.split('/').last())
+        .and_then(|s| s.split('/').next_back())
         .filter(|s| !s.is_empty())
         .unwrap_or("speedtest_file.dat")
         .to_string()
@@ -0,0 +1,232 @@
+// Offline geographic server ranking.
+// Computes great-circle distances from a home location to each server's lat/lon, with a
+// small embedded city table so a location can be given by name without any network calls.
+
+use crate::servers::ServerMetadata;
+
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone)]
+pub struct Location {
+    pub lat: f64,
+    pub lon: f64,
+    // Shown in menus and messages, e.g. "Berlin, Germany" or "52.52, 13.40"
+    pub label: String,
+}
+
+pub struct City {
+    pub name: &'static str,
+    pub country: &'static str,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+// Great-circle distance in kilometres using the Haversine formula
+pub fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+// Matches "Berlin" or "Berlin, Germany", ignoring case
+pub fn find_city(query: &str) -> Option<&'static City> {
+    let query = query.trim().to_lowercase();
+    let (name, country) = match query.split_once(',') {
+        Some((name, country)) => (name.trim().to_string(), Some(country.trim().to_string())),
+        None => (query.clone(), None),
+    };
+
+    CITIES.iter().find(|city| {
+        city.name.to_lowercase() == name
+            && country.as_ref().is_none_or(|c| city.country.to_lowercase() == *c)
+    })
+}
+
+impl From<&City> for Location {
+    fn from(city: &City) -> Self {
+        Location {
+            lat: city.lat,
+            lon: city.lon,
+            label: format!("{}, {}", city.name, city.country),
+        }
+    }
+}
+
+// Servers with coordinates, nearest first, paired with their distance in kilometres.
+// Servers without lat/lon (global CDNs, multi-region entries) are left out.
+pub fn rank_by_distance(servers: &[ServerMetadata], home: &Location) -> Vec<(ServerMetadata, f64)> {
+    let mut ranked: Vec<(ServerMetadata, f64)> = servers
+        .iter()
+        .filter_map(|s| match (s.lat, s.lon) {
+            (Some(lat), Some(lon)) => Some((s.clone(), haversine_km(home.lat, home.lon, lat, lon))),
+            _ => None,
+        })
+        .collect();
+
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+    ranked
+}
+
+pub const CITIES: &[City] = &[
+    // Europe
+    City { name: "Amsterdam", country: "Netherlands", lat: 52.3676, lon: 4.9041 },
+    City { name: "Athens", country: "Greece", lat: 37.9838, lon: 23.7275 },
+    City { name: "Barcelona", country: "Spain", lat: 41.3874, lon: 2.1686 },
+    City { name: "Berlin", country: "Germany", lat: 52.5200, lon: 13.4050 },
+    City { name: "Brussels", country: "Belgium", lat: 50.8503, lon: 4.3517 },
+    City { name: "Bucharest", country: "Romania", lat: 44.4268, lon: 26.1025 },
+    City { name: "Budapest", country: "Hungary", lat: 47.4979, lon: 19.0402 },
+    City { name: "Copenhagen", country: "Denmark", lat: 55.6761, lon: 12.5683 },
+    City { name: "Dublin", country: "Ireland", lat: 53.3498, lon: -6.2603 },
+    City { name: "Edinburgh", country: "United Kingdom", lat: 55.9533, lon: -3.1883 },
+    City { name: "Frankfurt", country: "Germany", lat: 50.1109, lon: 8.6821 },
+    City { name: "Hamburg", country: "Germany", lat: 53.5511, lon: 9.9937 },
+    City { name: "Helsinki", country: "Finland", lat: 60.1695, lon: 24.9354 },
+    City { name: "Istanbul", country: "Turkey", lat: 41.0082, lon: 28.9784 },
+    City { name: "Kyiv", country: "Ukraine", lat: 50.4501, lon: 30.5234 },
+    City { name: "Lisbon", country: "Portugal", lat: 38.7223, lon: -9.1393 },
+    City { name: "London", country: "United Kingdom", lat: 51.5074, lon: -0.1278 },
+    City { name: "Madrid", country: "Spain", lat: 40.4168, lon: -3.7038 },
+    City { name: "Manchester", country: "United Kingdom", lat: 53.4808, lon: -2.2426 },
+    City { name: "Milan", country: "Italy", lat: 45.4642, lon: 9.1900 },
+    City { name: "Munich", country: "Germany", lat: 48.1351, lon: 11.5820 },
+    City { name: "Nuremberg", country: "Germany", lat: 49.4521, lon: 11.0767 },
+    City { name: "Oslo", country: "Norway", lat: 59.9139, lon: 10.7522 },
+    City { name: "Paris", country: "France", lat: 48.8566, lon: 2.3522 },
+    City { name: "Prague", country: "Czech Republic", lat: 50.0755, lon: 14.4378 },
+    City { name: "Riga", country: "Latvia", lat: 56.9496, lon: 24.1052 },
+    City { name: "Rome", country: "Italy", lat: 41.9028, lon: 12.4964 },
+    City { name: "Sofia", country: "Bulgaria", lat: 42.6977, lon: 23.3219 },
+    City { name: "Stockholm", country: "Sweden", lat: 59.3293, lon: 18.0686 },
+    City { name: "Tallinn", country: "Estonia", lat: 59.4370, lon: 24.7536 },
+    City { name: "Vienna", country: "Austria", lat: 48.2082, lon: 16.3738 },
+    City { name: "Vilnius", country: "Lithuania", lat: 54.6872, lon: 25.2797 },
+    City { name: "Warsaw", country: "Poland", lat: 52.2297, lon: 21.0122 },
+    City { name: "Zurich", country: "Switzerland", lat: 47.3769, lon: 8.5417 },
+    // North America
+    City { name: "Atlanta", country: "USA", lat: 33.7490, lon: -84.3880 },
+    City { name: "Austin", country: "USA", lat: 30.2672, lon: -97.7431 },
+    City { name: "Boston", country: "USA", lat: 42.3601, lon: -71.0589 },
+    City { name: "Chicago", country: "USA", lat: 41.8781, lon: -87.6298 },
+    City { name: "Dallas", country: "USA", lat: 32.7767, lon: -96.7970 },
+    City { name: "Denver", country: "USA", lat: 39.7392, lon: -104.9903 },
+    City { name: "Houston", country: "USA", lat: 29.7604, lon: -95.3698 },
+    City { name: "Las Vegas", country: "USA", lat: 36.1699, lon: -115.1398 },
+    City { name: "Los Angeles", country: "USA", lat: 34.0522, lon: -118.2437 },
+    City { name: "Mexico City", country: "Mexico", lat: 19.4326, lon: -99.1332 },
+    City { name: "Miami", country: "USA", lat: 25.7617, lon: -80.1918 },
+    City { name: "Minneapolis", country: "USA", lat: 44.9778, lon: -93.2650 },
+    City { name: "Montreal", country: "Canada", lat: 45.5017, lon: -73.5673 },
+    City { name: "New York", country: "USA", lat: 40.7128, lon: -74.0060 },
+    City { name: "Philadelphia", country: "USA", lat: 39.9526, lon: -75.1652 },
+    City { name: "Phoenix", country: "USA", lat: 33.4484, lon: -112.0740 },
+    City { name: "Portland", country: "USA", lat: 45.5152, lon: -122.6784 },
+    City { name: "San Francisco", country: "USA", lat: 37.7749, lon: -122.4194 },
+    City { name: "San Jose", country: "USA", lat: 37.3382, lon: -121.8863 },
+    City { name: "Seattle", country: "USA", lat: 47.6062, lon: -122.3321 },
+    City { name: "Toronto", country: "Canada", lat: 43.6532, lon: -79.3832 },
+    City { name: "Vancouver", country: "Canada", lat: 49.2827, lon: -123.1207 },
+    City { name: "Washington", country: "USA", lat: 38.9072, lon: -77.0369 },
+    // South America
+    City { name: "Bogota", country: "Colombia", lat: 4.7110, lon: -74.0721 },
+    City { name: "Buenos Aires", country: "Argentina", lat: -34.6037, lon: -58.3816 },
+    City { name: "Lima", country: "Peru", lat: -12.0464, lon: -77.0428 },
+    City { name: "Rio de Janeiro", country: "Brazil", lat: -22.9068, lon: -43.1729 },
+    City { name: "Santiago", country: "Chile", lat: -33.4489, lon: -70.6693 },
+    City { name: "Sao Paulo", country: "Brazil", lat: -23.5505, lon: -46.6333 },
+    // Asia
+    City { name: "Bangalore", country: "India", lat: 12.9716, lon: 77.5946 },
+    City { name: "Bangkok", country: "Thailand", lat: 13.7563, lon: 100.5018 },
+    City { name: "Beijing", country: "China", lat: 39.9042, lon: 116.4074 },
+    City { name: "Chennai", country: "India", lat: 13.0827, lon: 80.2707 },
+    City { name: "Delhi", country: "India", lat: 28.7041, lon: 77.1025 },
+    City { name: "Hanoi", country: "Vietnam", lat: 21.0278, lon: 105.8342 },
+    City { name: "Ho Chi Minh City", country: "Vietnam", lat: 10.8231, lon: 106.6297 },
+    City { name: "Hong Kong", country: "China", lat: 22.3193, lon: 114.1694 },
+    City { name: "Jakarta", country: "Indonesia", lat: -6.2088, lon: 106.8456 },
+    City { name: "Kuala Lumpur", country: "Malaysia", lat: 3.1390, lon: 101.6869 },
+    City { name: "Manila", country: "Philippines", lat: 14.5995, lon: 120.9842 },
+    City { name: "Mumbai", country: "India", lat: 19.0760, lon: 72.8777 },
+    City { name: "Osaka", country: "Japan", lat: 34.6937, lon: 135.5023 },
+    City { name: "Seoul", country: "South Korea", lat: 37.5665, lon: 126.9780 },
+    City { name: "Shanghai", country: "China", lat: 31.2304, lon: 121.4737 },
+    City { name: "Singapore", country: "Singapore", lat: 1.3521, lon: 103.8198 },
+    City { name: "Taipei", country: "Taiwan", lat: 25.0330, lon: 121.5654 },
+    City { name: "Tokyo", country: "Japan", lat: 35.6762, lon: 139.6503 },
+    // Middle East
+    City { name: "Dubai", country: "United Arab Emirates", lat: 25.2048, lon: 55.2708 },
+    City { name: "Riyadh", country: "Saudi Arabia", lat: 24.7136, lon: 46.6753 },
+    City { name: "Tel Aviv", country: "Israel", lat: 32.0853, lon: 34.7818 },
+    // Africa
+    City { name: "Cairo", country: "Egypt", lat: 30.0444, lon: 31.2357 },
+    City { name: "Cape Town", country: "South Africa", lat: -33.9249, lon: 18.4241 },
+    City { name: "Johannesburg", country: "South Africa", lat: -26.2041, lon: 28.0473 },
+    City { name: "Lagos", country: "Nigeria", lat: 6.5244, lon: 3.3792 },
+    City { name: "Nairobi", country: "Kenya", lat: -1.2921, lon: 36.8219 },
+    // Oceania
+    City { name: "Auckland", country: "New Zealand", lat: -36.8485, lon: 174.7633 },
+    City { name: "Brisbane", country: "Australia", lat: -27.4698, lon: 153.0251 },
+    City { name: "Melbourne", country: "Australia", lat: -37.8136, lon: 144.9631 },
+    City { name: "Perth", country: "Australia", lat: -31.9505, lon: 115.8605 },
+    City { name: "Sydney", country: "Australia", lat: -33.8688, lon: 151.2093 },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distance(from: &str, to: &str) -> f64 {
+        let (a, b) = (find_city(from).unwrap(), find_city(to).unwrap());
+        haversine_km(a.lat, a.lon, b.lat, b.lon)
+    }
+
+    #[test]
+    fn known_city_distances() {
+        // Published great-circle distances, within half a percent
+        for (from, to, km) in [("London", "Paris", 344.0), ("New York", "London", 5570.0), ("Berlin", "Paris", 878.0), ("Sydney", "Tokyo", 7826.0)] {
+            let d = distance(from, to);
+            assert!((d - km).abs() / km < 0.005, "{} to {}: {:.1} km", from, to, d);
+            assert!((distance(to, from) - d).abs() < 1e-9);
+        }
+        assert_eq!(distance("Berlin", "Berlin"), 0.0);
+    }
+
+    #[test]
+    fn city_lookup_ignores_case_and_spacing() {
+        assert_eq!(find_city("berlin").unwrap().name, "Berlin");
+        assert_eq!(find_city("  PARIS , france ").unwrap().name, "Paris");
+        assert_eq!(find_city("new york").unwrap().country, "USA");
+    }
+
+    #[test]
+    fn city_lookup_misses() {
+        assert!(find_city("Atlantis").is_none());
+        assert!(find_city("berlin, france").is_none());
+        assert!(find_city("").is_none());
+    }
+
+    #[test]
+    fn servers_without_coordinates_are_not_ranked() {
+        let server = |name: &str, lat: Option<f64>, lon: Option<f64>| ServerMetadata {
+            name: name.to_string(),
+            url: format!("https://{}.example.com/100MB.bin", name),
+            provider: None,
+            location: None,
+            region: None,
+            lat,
+            lon,
+            file_size: None,
+            enabled: true,
+            upload_url: None,
+            upload_method: None,
+        };
+        let servers = [server("paris", Some(48.8566), Some(2.3522)), server("cdn", None, None), server("london", Some(51.5074), Some(-0.1278))];
+        let home = Location::from(find_city("Amsterdam").unwrap());
+
+        let ranked = rank_by_distance(&servers, &home);
+        let names: Vec<&str> = ranked.iter().map(|(s, _)| s.name.as_str()).collect();
+        assert_eq!(names, ["london", "paris"]);
+    }
+}
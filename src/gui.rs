@@ -7,6 +7,8 @@ pub mod freya_ui {
     use crate::config::{Config, SpeedUnit};
     use crate::servers::{get_merged_server_list, load_local_server_data};
     use crate::downloader::DownloadResult;
+    use crate::latency::Bufferbloat;
+    use crate::timing::PhaseTimings;
     
     #[derive(Clone, Debug)]
     pub struct TestResult {
@@ -16,14 +18,16 @@ pub mod freya_ui {
         pub total_time: f64,
         pub connect_time: f64,
         pub ttfb: f64,
+        pub timings: PhaseTimings,
         pub speed_mbps: f64,
         pub speed_mb_s: f64,
+        pub bufferbloat: Option<Bufferbloat>,
     }
     
     impl From<(DownloadResult, &str)> for TestResult {
         fn from((result, server_name): (DownloadResult, &str)) -> Self {
-            let mbps = (result.bytes_downloaded as f64 * 8.0 / result.total_time) / 1_000_000.0;
-            let mb_s = (result.bytes_downloaded as f64 / result.total_time) / 1_000_000.0;
+            let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
+            let mb_s = result.bytes_per_second() / 1_000_000.0;
             
             TestResult {
                 server_name: server_name.to_string(),
@@ -32,29 +36,33 @@ pub mod freya_ui {
                 total_time: result.total_time,
                 connect_time: result.connect_time,
                 ttfb: result.ttfb,
+                timings: result.timings,
                 speed_mbps: mbps,
                 speed_mb_s: mb_s,
+                bufferbloat: result.latency.and_then(|l| l.bufferbloat()),
             }
         }
     }
     
-    pub fn launch_gui(_config: Config) {
+    pub fn launch_gui(config: Config) {
+        // The config main already loaded is handed to the app as context state
         launch_cfg(
             app,
-            LaunchConfig::<()>::new()
+            LaunchConfig::<Config>::new()
                 .with_title("Speedo - Network Speed Test")
-                .with_size(800.0, 650.0),
+                .with_size(800.0, 650.0)
+                .with_state(config),
         );
     }
     
     #[component]
     fn app() -> Element {
-        let config = use_signal(|| crate::config::load_config());
+        let config = use_signal(|| consume_context::<Config>());
         
         // Load servers once on initialization
-        let servers = use_signal(|| {
+        let servers = use_signal(move || {
             let server_data = load_local_server_data();
-            get_merged_server_list(&server_data)
+            get_merged_server_list(&server_data, &config.read())
         });
         
         let mut selected_server = use_signal(|| 0usize);
@@ -95,14 +103,25 @@ pub mod freya_ui {
                 status_message.set(format!("Testing {}...", server_clone.name));
                 
                 spawn(async move {
-                    match crate::downloader::download_file_with_progress(
+                    let result = crate::downloader::download_file_with_progress(
                         &server_clone.url,
                         None,
                         &config_clone.user_agent,
                         speed_unit,
                         false, // Disable progress bar in GUI mode
-                    ).await {
+                    ).await;
+                    // Stamped when the test ends, like every other history record
+                    let record = crate::history::HistoryRecord::new(
+                        &server_clone.name,
+                        &server_clone.url,
+                        server_clone.provider.as_deref(),
+                        crate::history::HistoryMode::Download,
+                        speed_unit,
+                    );
+                    match result {
                         Ok(result) => {
+                            crate::servers::record_health(&server_clone.url, result.health_sample()).ok();
+                            crate::history::save(&config_clone, record.with_download(&result));
                             let test_result = TestResult::from((result, server_clone.name.as_str()));
                             last_result.set(Some(test_result.clone()));
                             status_message.set(format!(
@@ -112,6 +131,8 @@ pub mod freya_ui {
                             ));
                         }
                         Err(e) => {
+                            crate::servers::record_health(&server_clone.url, crate::servers::HealthSample::failure()).ok();
+                            crate::history::save(&config_clone, record.with_error(e.as_ref()));
                             status_message.set(format!("Error: {}", e));
                         }
                     }
@@ -349,6 +370,29 @@ pub mod freya_ui {
                                 label: "TTFB",
                                 value: format!("{:.3}s", result.ttfb)
                             }
+                            
+                            ResultRow {
+                                label: "DNS / Connect",
+                                value: format!(
+                                    "{:.3}s / {:.3}s ({})",
+                                    result.timings.dns,
+                                    result.timings.connect,
+                                    result.timings.connect_label()
+                                )
+                            }
+                            
+                            ResultRow {
+                                label: "Wait / Transfer",
+                                value: format!("{:.3}s / {:.3}s", result.timings.server_wait, result.timings.transfer)
+                            }
+                            
+                            ResultRow {
+                                label: "Bufferbloat",
+                                value: match result.bufferbloat {
+                                    Some(b) => format!("{} (+{:.1} ms under load)", b.grade.as_str(), b.latency_increase),
+                                    None => "n/a".to_string(),
+                                }
+                            }
                         }
                     }
                 }
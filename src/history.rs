@@ -0,0 +1,369 @@
+// Local history of test runs and the `speedo history` command.
+// Every test appends one JSON line to history.jsonl next to the server cache, so runs can be
+// listed, filtered and exported later.
+
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use crate::config::{Config, SpeedUnit};
+use crate::downloader::DownloadResult;
+use crate::output::{self, OutputFormat};
+use crate::stats::HistoryStats;
+use crate::uploader::UploadResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryMode {
+    Download,
+    Upload,
+    /// Download and upload against one server
+    Bidir,
+}
+
+impl HistoryMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryMode::Download => "download",
+            HistoryMode::Upload => "upload",
+            HistoryMode::Bidir => "bidir",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    Ok,
+    Failed,
+}
+
+impl RunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunStatus::Ok => "ok",
+            RunStatus::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: DateTime<Utc>,
+    pub server: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub mode: HistoryMode,
+    // Unit the speed was displayed in when the test ran; stored speeds are always Mbps
+    pub speed_unit: String,
+    pub status: RunStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    #[serde(default)]
+    pub bytes: u64,
+    #[serde(default)]
+    pub total_time: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub download_mbps: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_mbps: Option<f64>,
+    // Idle round trip, and the round trip while the download was running
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub loaded_latency_ms: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jitter_ms: Option<f64>,
+    #[serde(default = "default_connections")]
+    pub connections: usize,
+    // Servers tested at once when the run was part of a parallel batch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parallel: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+fn default_connections() -> usize {
+    1
+}
+
+impl HistoryRecord {
+    pub fn new(server: &str, url: &str, provider: Option<&str>, mode: HistoryMode, speed_unit: SpeedUnit) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            server: server.to_string(),
+            url: url.to_string(),
+            provider: provider.map(str::to_string),
+            mode,
+            speed_unit: speed_unit.as_str().to_string(),
+            status: RunStatus::Ok,
+            status_code: None,
+            bytes: 0,
+            total_time: 0.0,
+            download_mbps: None,
+            upload_mbps: None,
+            latency_ms: None,
+            loaded_latency_ms: None,
+            jitter_ms: None,
+            connections: 1,
+            parallel: None,
+            error: None,
+        }
+    }
+
+    pub fn with_download(mut self, result: &DownloadResult) -> Self {
+        let ok = (result.status_code == 200 || result.status_code == 206) && result.bytes_downloaded > 0;
+        let latency = result.latency.unwrap_or_default();
+        self.status_code = Some(result.status_code);
+        self.bytes += result.bytes_downloaded;
+        self.total_time += result.total_time;
+        self.download_mbps = ok.then(|| result.bytes_per_second() * 8.0 / 1_000_000.0);
+        self.latency_ms = latency.idle.map(|s| s.avg);
+        self.loaded_latency_ms = latency.loaded.map(|s| s.avg);
+        self.jitter_ms = latency.idle.map(|s| s.jitter);
+        self.connections = result.streams.len().max(1);
+        if !ok {
+            self.fail(format!("HTTP {}", result.status_code));
+        }
+        self
+    }
+
+    pub fn with_upload(mut self, result: &UploadResult) -> Self {
+        let ok = (200..300).contains(&result.status_code);
+        // A bidirectional run keeps the download's status code
+        self.status_code = self.status_code.or(Some(result.status_code));
+        self.bytes += result.bytes_uploaded;
+        self.total_time = self.total_time.max(result.total_time);
+        self.upload_mbps = ok.then(|| result.bytes_per_second() * 8.0 / 1_000_000.0);
+        if !ok {
+            self.fail(format!("upload HTTP {}", result.status_code));
+        }
+        self
+    }
+
+    pub fn with_error(mut self, error: &dyn std::error::Error) -> Self {
+        self.fail(error.to_string());
+        self
+    }
+
+    pub fn with_parallel(mut self, parallel: usize) -> Self {
+        self.parallel = (parallel > 1).then_some(parallel);
+        self
+    }
+
+    fn fail(&mut self, error: String) {
+        self.status = RunStatus::Failed;
+        self.error.get_or_insert(error);
+    }
+
+    // The headline speed: download, or upload for upload-only runs
+    pub fn speed_mbps(&self) -> Option<f64> {
+        self.download_mbps.or(self.upload_mbps)
+    }
+}
+
+pub fn history_path() -> PathBuf {
+    if let Some(data_dir) = dirs::data_local_dir() {
+        data_dir.join("speedo").join("history.jsonl")
+    } else {
+        PathBuf::from(".speedo_history.jsonl")
+    }
+}
+
+pub fn append(record: &HistoryRecord) -> std::io::Result<()> {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    // One write per line, so concurrent speedo processes cannot interleave records
+    writeln!(file, "{}", serde_json::to_string(record)?)
+}
+
+// Appends a finished test to the history, unless `history = false` in the config
+pub fn save(config: &Config, record: HistoryRecord) {
+    if !config.history {
+        return;
+    }
+    if let Err(e) = append(&record) {
+        use colored::*;
+        eprintln!("{}", format!("Warning: Failed to save test history: {}", e).yellow());
+    }
+}
+
+// Every stored run, oldest first, and how many lines could not be read
+pub fn load() -> std::io::Result<(Vec<HistoryRecord>, usize)> {
+    let path = history_path();
+    if !path.exists() {
+        return Ok((Vec::new(), 0));
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    let mut records = Vec::new();
+    let mut skipped = 0;
+    for line in contents.lines().filter(|l| !l.trim().is_empty()) {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(_) => skipped += 1,
+        }
+    }
+    Ok((records, skipped))
+}
+
+// "7d" or "12h" (that long ago), a date such as "2025-11-01" (local midnight), or an RFC 3339 time
+pub fn parse_when(s: &str) -> Result<DateTime<Utc>, String> {
+    let s = s.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map(|t| t.with_timezone(&Utc))
+            .ok_or_else(|| format!("invalid date '{}'", s));
+    }
+    if let Ok(time) = DateTime::parse_from_rfc3339(s) {
+        return Ok(time.with_timezone(&Utc));
+    }
+
+    let ago = crate::config::parse_duration(s)
+        .map_err(|_| format!("invalid time '{}' (use e.g. 30d, 12h, 2025-11-01)", s))?;
+    chrono::Duration::from_std(ago)
+        .ok()
+        .and_then(|ago| Utc::now().checked_sub_signed(ago))
+        .ok_or_else(|| format!("'{}' is too long ago", s))
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct HistoryFilter {
+    /// Only runs since this long ago (e.g. 7d) or this date (e.g. 2025-11-01)
+    #[arg(long, value_name = "WHEN", value_parser = parse_when, global = true)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only runs before this long ago or this date
+    #[arg(long, value_name = "WHEN", value_parser = parse_when, global = true)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Only runs against servers whose name or URL contains this
+    #[arg(long, value_name = "SERVER", global = true)]
+    pub server: Option<String>,
+
+    /// Only runs of this kind
+    #[arg(long, value_enum, global = true)]
+    pub mode: Option<HistoryMode>,
+}
+
+impl HistoryFilter {
+    pub fn matches(&self, record: &HistoryRecord) -> bool {
+        let server = self.server.as_ref().map(|s| s.to_lowercase());
+        self.since.is_none_or(|since| record.timestamp >= since)
+            && self.until.is_none_or(|until| record.timestamp < until)
+            && server.is_none_or(|s| record.server.to_lowercase().contains(&s) || record.url.to_lowercase().contains(&s))
+            && self.mode.is_none_or(|mode| record.mode == mode)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    pub command: Option<HistoryCommand>,
+
+    #[command(flatten)]
+    pub filter: HistoryFilter,
+
+    /// Number of most recent runs to list
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 20)]
+    pub limit: usize,
+
+    /// Chart speed and latency of every matching run over time instead of listing runs
+    #[arg(long, conflicts_with_all = ["format", "json", "limit"])]
+    pub chart: bool,
+
+    /// Output format: json, csv, or human (default)
+    #[arg(long, value_name = "FORMAT", global = true)]
+    pub format: Option<String>,
+
+    /// Output JSON format (shorthand for --format json)
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum HistoryCommand {
+    /// Export every matching run: JSON Lines by default, or --format json/csv
+    Export {
+        /// Write to this file instead of stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Speed statistics per server and hour of day, with week-over-week trends (default: --since 30d)
+    Stats,
+}
+
+pub fn run(args: HistoryArgs, config: &Config, speed_unit: SpeedUnit) -> Result<(), Box<dyn std::error::Error>> {
+    if args.chart && args.command.is_some() {
+        return Err("--chart charts the matching runs and cannot be combined with `stats` or `export`".into());
+    }
+
+    let output_format = if args.json {
+        Some(OutputFormat::Json)
+    } else {
+        args.format.as_deref().map(OutputFormat::from_string)
+    };
+
+    let now = Utc::now();
+    let mut filter = args.filter;
+    if let Some(HistoryCommand::Stats) = args.command {
+        // Statistics cover the last 30 days unless told otherwise
+        filter.since.get_or_insert(now - chrono::Duration::days(30));
+    }
+
+    let (records, skipped) = load()?;
+    if skipped > 0 {
+        use colored::*;
+        eprintln!("{}", format!("Warning: {} history lines could not be read and were skipped", skipped).yellow());
+    }
+    let records: Vec<HistoryRecord> = records.into_iter().filter(|r| filter.matches(r)).collect();
+
+    match args.command {
+        None if args.chart => {
+            let server_list = crate::servers::get_full_server_list(&crate::servers::load_local_server_data(), config);
+            crate::ui::print_history_chart(&records, speed_unit, &server_list);
+        }
+        None => {
+            let shown = &records[records.len().saturating_sub(args.limit)..];
+            match output_format.unwrap_or(OutputFormat::Human) {
+                OutputFormat::Json => println!("{}", output::history_json(shown, false)?),
+                OutputFormat::JsonCompact => println!("{}", output::history_json(shown, true)?),
+                OutputFormat::Csv => print!("{}", output::history_csv(shown)),
+                OutputFormat::Human => crate::ui::print_history(shown, records.len(), speed_unit),
+            }
+        }
+        Some(HistoryCommand::Export { output }) => {
+            let contents = match output_format {
+                Some(OutputFormat::Json) => output::history_json(&records, false)? + "\n",
+                Some(OutputFormat::JsonCompact) => output::history_json(&records, true)? + "\n",
+                Some(OutputFormat::Csv) => output::history_csv(&records),
+                Some(OutputFormat::Human) | None => records.iter()
+                    .map(|r| serde_json::to_string(r).map(|line| line + "\n"))
+                    .collect::<Result<String, _>>()?,
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, contents)?;
+                    eprintln!("Exported {} runs to {}", records.len(), path.display());
+                }
+                None => print!("{}", contents),
+            }
+        }
+        Some(HistoryCommand::Stats) => {
+            let stats = HistoryStats::compute(&records, filter.since.unwrap_or(now), filter.until.unwrap_or(now));
+            match output_format.unwrap_or(OutputFormat::Human) {
+                OutputFormat::Json => output::print_history_stats_json(&stats, false)?,
+                OutputFormat::JsonCompact => output::print_history_stats_json(&stats, true)?,
+                OutputFormat::Csv => output::print_history_stats_csv(&stats),
+                OutputFormat::Human => crate::ui::print_history_stats(&stats, speed_unit),
+            }
+        }
+    }
+
+    Ok(())
+}
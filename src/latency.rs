@@ -0,0 +1,204 @@
+// Round-trip latency and jitter probing.
+// Times repeated TCP connects to the test server while the link is idle and again while
+// the download is running, so queueing delay under load shows up next to the baseline.
+
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+
+const IDLE_PROBES: usize = 8;
+const IDLE_INTERVAL: Duration = Duration::from_millis(50);
+const LOADED_INTERVAL: Duration = Duration::from_millis(250);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const RANKING_PROBES: usize = 3;
+
+// Round-trip times in milliseconds. Jitter is the mean difference between consecutive samples.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub lost: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub jitter: f64,
+}
+
+impl LatencyStats {
+    fn from_samples(samples: &[f64], lost: usize) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        let min = samples.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = samples.iter().cloned().fold(0.0, f64::max);
+        let avg = samples.iter().sum::<f64>() / samples.len() as f64;
+        let jitter = if samples.len() > 1 {
+            samples.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (samples.len() - 1) as f64
+        } else {
+            0.0
+        };
+
+        Some(Self {
+            samples: samples.len(),
+            lost,
+            min,
+            avg,
+            max,
+            jitter,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Latency {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle: Option<LatencyStats>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub loaded: Option<LatencyStats>,
+}
+
+impl Latency {
+    // Extra round-trip time in milliseconds caused by the transfer filling queues
+    pub fn increase(&self) -> Option<f64> {
+        match (self.idle, self.loaded) {
+            (Some(idle), Some(loaded)) => Some((loaded.avg - idle.avg).max(0.0)),
+            _ => None,
+        }
+    }
+
+    pub fn bufferbloat(&self) -> Option<Bufferbloat> {
+        self.increase().map(|increase| Bufferbloat {
+            grade: BufferbloatGrade::from_increase(increase),
+            latency_increase: increase,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum BufferbloatGrade {
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
+impl BufferbloatGrade {
+    // Thresholds on the average latency increase under load, in milliseconds
+    fn from_increase(increase: f64) -> Self {
+        match increase {
+            x if x < 30.0 => BufferbloatGrade::A,
+            x if x < 60.0 => BufferbloatGrade::B,
+            x if x < 200.0 => BufferbloatGrade::C,
+            x if x < 400.0 => BufferbloatGrade::D,
+            _ => BufferbloatGrade::F,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BufferbloatGrade::A => "A",
+            BufferbloatGrade::B => "B",
+            BufferbloatGrade::C => "C",
+            BufferbloatGrade::D => "D",
+            BufferbloatGrade::F => "F",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Bufferbloat {
+    pub grade: BufferbloatGrade,
+    // Milliseconds
+    pub latency_increase: f64,
+}
+
+// Address the probes connect to: the first resolved address of the URL's host and port
+pub async fn probe_target(url: &str) -> Option<SocketAddr> {
+    let parsed = reqwest::Url::parse(url).ok()?;
+    let host = parsed.host_str()?.trim_start_matches('[').trim_end_matches(']').to_string();
+    let port = parsed.port_or_known_default()?;
+    let mut addrs = tokio::net::lookup_host((host.as_str(), port)).await.ok()?;
+    addrs.next()
+}
+
+// A completed TCP handshake is one round trip, so its duration is the RTT
+async fn probe_once(addr: SocketAddr) -> Option<f64> {
+    let start = Instant::now();
+    match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => Some(start.elapsed().as_secs_f64() * 1000.0),
+        _ => None,
+    }
+}
+
+// Best of a few connect times, so ranking servers reflects distance rather than one noisy sample
+pub async fn quick_rtt(url: &str) -> Option<f64> {
+    let lookup = tokio::time::timeout(PROBE_TIMEOUT, probe_target(url));
+    let addr = lookup.await.ok().flatten()?;
+
+    let mut best: Option<f64> = None;
+    for _ in 0..RANKING_PROBES {
+        match probe_once(addr).await {
+            Some(rtt) => best = Some(best.map_or(rtt, |b| b.min(rtt))),
+            // An unreachable server will not answer the next probe either
+            None if best.is_none() => return None,
+            None => {}
+        }
+    }
+    best
+}
+
+pub async fn measure_idle(addr: SocketAddr) -> Option<LatencyStats> {
+    let mut samples = Vec::with_capacity(IDLE_PROBES);
+    let mut lost = 0;
+
+    for i in 0..IDLE_PROBES {
+        if i > 0 {
+            tokio::time::sleep(IDLE_INTERVAL).await;
+        }
+        match probe_once(addr).await {
+            Some(rtt) => samples.push(rtt),
+            None => lost += 1,
+        }
+    }
+
+    LatencyStats::from_samples(&samples, lost)
+}
+
+// Probes in the background until finished, for latency while a transfer is running
+pub struct LoadedProbe {
+    samples: Arc<Mutex<(Vec<f64>, usize)>>,
+    handle: JoinHandle<()>,
+}
+
+impl LoadedProbe {
+    pub fn start(addr: SocketAddr) -> Self {
+        let samples = Arc::new(Mutex::new((Vec::new(), 0)));
+        let task_samples = samples.clone();
+
+        let handle = tokio::spawn(async move {
+            loop {
+                // Wait first so the transfer is under way before the first sample
+                tokio::time::sleep(LOADED_INTERVAL).await;
+                let rtt = probe_once(addr).await;
+                let mut samples = task_samples.lock().unwrap();
+                match rtt {
+                    Some(rtt) => samples.0.push(rtt),
+                    None => samples.1 += 1,
+                }
+            }
+        });
+
+        Self { samples, handle }
+    }
+
+    // A probe still in flight did not finish under load, so it is dropped rather than waited for
+    pub fn finish(self) -> Option<LatencyStats> {
+        self.handle.abort();
+        let samples = self.samples.lock().unwrap();
+        LatencyStats::from_samples(&samples.0, samples.1)
+    }
+}
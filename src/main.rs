@@ -1,22 +1,67 @@
 // Application entry point and command-line argument handling.
 // Routes execution to interactive mode, non-interactive mode, or URL download.
 
+mod batch;
+mod bidir;
+mod chart;
 mod config;
 mod downloader;
+mod geo;
 mod gui;
+mod history;
+mod latency;
+mod manage;
 mod output;
 mod servers;
+mod sources;
+mod stats;
+mod timing;
 mod ui;
+mod uploader;
+mod validate;
+mod verify;
 
+use bidir::{run_bidir, BidirMode};
 use clap::Parser;
-use config::{load_config, SpeedUnit};
-use downloader::download_file;
+use colored::*;
+use config::{load_config, parse_duration, parse_size, parse_test_duration, SpeedUnit};
+use downloader::{download_file, DownloadOptions};
 use output::OutputFormat;
 use ui::{show_menu, print_results, print_speed_only, print_download_header, wait_for_continue, ServerSelection};
+use uploader::{upload_data, UploadOptions};
+
+// What a speed test measures
+#[derive(Debug, Clone, Copy)]
+enum TestMode {
+    Download,
+    Upload(UploadOptions),
+    Bidir(BidirMode, UploadOptions),
+}
+
+// Settings shared by every test in one run
+#[derive(Clone, Copy)]
+struct TestSettings<'a> {
+    config: &'a crate::config::Config,
+    speed_unit: SpeedUnit,
+    output_format: OutputFormat,
+    download_options: DownloadOptions,
+    test_mode: TestMode,
+}
+
+// How a non-interactive run picks the server(s) to test
+enum ServerPick {
+    First,
+    Auto,
+    Nearest(usize, geo::Location),
+}
 
 #[derive(Parser)]
 #[command(version, about = "A fast network speed test tool", long_about = None)]
+#[command(group(clap::ArgGroup::new("batch").multiple(true).conflicts_with_all(["url", "interactive", "auto", "nearest", "near", "bidir"])))]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+    
     /// URL to download (saves file to current directory)
     #[arg(value_name = "URL")]
     url: Option<String>,
@@ -33,6 +78,70 @@ struct Args {
     #[arg(short, long, value_name = "UNIT")]
     speed_unit: Option<String>,
     
+    /// Number of parallel connections (segments when downloading a URL)
+    #[arg(short, long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..=64))]
+    connections: u16,
+    
+    /// Resume a partially downloaded URL instead of starting over
+    #[arg(short = 'C', long = "continue")]
+    continue_download: bool,
+    
+    /// Stop each test after this long (e.g. 10s, 1m) instead of downloading the whole file
+    #[arg(short, long, value_name = "DURATION", value_parser = parse_test_duration)]
+    duration: Option<std::time::Duration>,
+    
+    /// Leading part of a --duration test excluded from the speed to skip TCP slow start [default: 2s or a fifth of the duration]
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, requires = "duration")]
+    warmup: Option<std::time::Duration>,
+    
+    /// Test against the server with the lowest latency instead of the first one
+    #[arg(short, long, conflicts_with_all = ["url", "interactive"])]
+    auto: bool,
+    
+    /// Test the N servers geographically closest to home (home_lat/home_lon or --near)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..), conflicts_with_all = ["url", "interactive", "auto"])]
+    nearest: Option<u16>,
+    
+    /// Use this city as home for nearest-server ranking, e.g. "Berlin" or "Paris, France"
+    #[arg(long, value_name = "CITY", conflicts_with_all = ["url", "auto"])]
+    near: Option<String>,
+    
+    /// Test every server in the list, one after another, and summarise best, worst and median
+    #[arg(long, group = "batch", conflicts_with_all = ["region", "provider", "servers"])]
+    all: bool,
+    
+    /// Test every server in this region (e.g. Europe); combines with --provider
+    #[arg(long, value_name = "REGION", group = "batch")]
+    region: Option<String>,
+    
+    /// Test every server from this provider (e.g. Hetzner)
+    #[arg(long, value_name = "PROVIDER", group = "batch")]
+    provider: Option<String>,
+    
+    /// Test these servers, given by name or URL and separated by commas
+    #[arg(long, value_name = "A,B,C", value_delimiter = ',', group = "batch", conflicts_with_all = ["region", "provider"])]
+    servers: Vec<String>,
+    
+    /// Run batch tests N servers at a time instead of one after another
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=32), requires = "batch")]
+    parallel: Option<u16>,
+    
+    /// Skip the idle and loaded latency probes
+    #[arg(long)]
+    no_latency: bool,
+    
+    /// Measure upload speed instead of download
+    #[arg(short, long)]
+    upload: bool,
+    
+    /// Amount of random data to send in an upload test (e.g. 25MB, 100MiB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size, default_value = "25MB")]
+    upload_size: u64,
+    
+    /// Test download and upload against one server, one after the other or at the same time
+    #[arg(long, value_name = "MODE", num_args = 0..=1, default_missing_value = "sequential", conflicts_with_all = ["upload", "url"])]
+    bidir: Option<BidirMode>,
+    
     /// Output format: json, csv, or human (default)
     #[arg(long, value_name = "FORMAT")]
     format: Option<String>,
@@ -49,11 +158,24 @@ struct Args {
     #[arg(long)]
     update_servers: bool,
     
+    /// Check every server in the list is reachable and serves the expected file
+    #[arg(long, conflicts_with = "url")]
+    verify_servers: bool,
+    
     /// Launch graphical user interface
     #[arg(short = 'g', long)]
     gui: bool,
 }
 
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Manage the server list: custom servers and enabled/disabled servers
+    #[command(subcommand)]
+    Servers(manage::ServersCommand),
+    /// List, filter and export past test runs
+    History(history::HistoryArgs),
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
     
@@ -72,17 +194,53 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 async fn async_main(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     let config = load_config();
     
+    if let Some(command) = args.command {
+        let result = match command {
+            Command::Servers(command) => manage::run(command, &config).await,
+            // History has its own --server filter; the batch list would otherwise be silently ignored
+            Command::History(_) if !args.servers.is_empty() => {
+                Err("--servers selects servers to test; filter the history with `speedo history --server NAME`".into())
+            }
+            Command::History(history_args) => {
+                let speed_unit = SpeedUnit::from_string(args.speed_unit.as_ref().unwrap_or(&config.speed_unit));
+                history::run(history_args, &config, speed_unit)
+            }
+        };
+        if let Err(e) = result {
+            eprintln!("{}", format!("Error: {}", e).red());
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    
     // Handle --update-servers command
     if args.update_servers {
-        return update_server_list().await;
+        return update_server_list(&config).await;
     }
     
     // Auto-update server list if cache is stale
     let mut server_data = servers::load_local_server_data();
-    if servers::should_update_cache(&server_data) {
-        if let Ok(remote_list) = servers::fetch_remote_server_list().await {
-            server_data.remote_list = Some(remote_list);
-            server_data.cache_timestamp = chrono::Utc::now();
+    let list_ttl = parse_duration(&config.server_list_ttl).unwrap_or_else(|e| {
+        eprintln!("{}", format!("Warning: invalid server_list_ttl ({}), using 7d", e).yellow());
+        std::time::Duration::from_secs(7 * 86400)
+    });
+    if servers::should_update_cache(&server_data, list_ttl) {
+        let outcomes = servers::refresh_server_sources(&mut server_data, &config).await;
+        for (source, outcome) in &outcomes {
+            match outcome {
+                Ok(update) => {
+                    if let Some(note) = schema_note(update) {
+                        eprintln!("{}", format!("Note: {}: {}", source, note).yellow());
+                    }
+                }
+                // Being offline is routine, but a list that fails its signature check is worth a warning
+                Err(e) if e.is::<servers::UntrustedServerList>() => {
+                    eprintln!("{}", format!("Warning: {}; keeping the cached server list", e).yellow());
+                }
+                Err(_) => {}
+            }
+        }
+        if outcomes.iter().any(|(_, outcome)| outcome.is_ok()) {
             servers::save_local_server_data(&server_data).ok();
         }
     }
@@ -91,6 +249,41 @@ async fn async_main(args: Args) -> Result<(), Box<dyn std::error::Error>> {
     let speed_unit_str = args.speed_unit.as_ref().unwrap_or(&config.speed_unit);
     let speed_unit = SpeedUnit::from_string(speed_unit_str);
     
+    // Simultaneous directions only tell us something while they overlap, so bound both
+    // to the same window instead of letting the smaller upload finish early
+    let duration = match args.bidir {
+        Some(BidirMode::Simultaneous) => args.duration.or(Some(std::time::Duration::from_secs(10))),
+        _ => args.duration,
+    };
+    
+    let warmup = match (duration, args.warmup) {
+        (Some(duration), Some(warmup)) if warmup >= duration => {
+            eprintln!("Error: --warmup must be shorter than --duration");
+            std::process::exit(2);
+        }
+        (_, Some(warmup)) => warmup,
+        (Some(duration), None) => (duration / 5).min(std::time::Duration::from_secs(2)),
+        (None, None) => std::time::Duration::ZERO,
+    };
+    
+    let download_options = DownloadOptions {
+        connections: args.connections as usize,
+        resume: args.continue_download,
+        duration,
+        warmup,
+        latency: !args.no_latency,
+    };
+    
+    let upload_options = UploadOptions {
+        size: args.upload_size,
+        duration,
+    };
+    let test_mode = match args.bidir {
+        Some(mode) => TestMode::Bidir(mode, upload_options),
+        None if args.upload => TestMode::Upload(upload_options),
+        None => TestMode::Download,
+    };
+    
     // Determine output format
     let output_format = if args.json {
         if args.compact {
@@ -104,10 +297,51 @@ async fn async_main(args: Args) -> Result<(), Box<dyn std::error::Error>> {
         OutputFormat::Human
     };
     
+    if args.verify_servers {
+        return verify_server_list(&server_data, &config, output_format).await;
+    }
+    
+    let menu = opens_menu(&args, config.interactive);
+    let batch = if args.all {
+        Some(batch::Selector::All)
+    } else if !args.servers.is_empty() {
+        Some(batch::Selector::Named(args.servers))
+    } else if args.region.is_some() || args.provider.is_some() {
+        Some(batch::Selector::Filter { region: args.region, provider: args.provider })
+    } else {
+        None
+    };
+    
     // If URL is provided, download it and save to current directory
     if let Some(url) = args.url {
+        if let TestMode::Upload(upload_options) = test_mode {
+            // A URL given with --upload is the upload endpoint itself
+            let show_progress = output_format == OutputFormat::Human;
+            let result = upload_data(&url, "POST", &config.user_agent, speed_unit, upload_options, show_progress).await;
+            let record = history::HistoryRecord::new("Custom URL", &url, None, history::HistoryMode::Upload, speed_unit);
+            history::save(&config, match &result {
+                Ok(result) => record.with_upload(result),
+                Err(e) => record.with_error(e.as_ref()),
+            });
+            print_upload_output(&result?, "Custom URL", &url, output_format, false, true)?;
+            return Ok(());
+        }
+        
+        if args.duration.is_some() {
+            eprintln!("{}", "Note: --duration only applies to speed tests; downloading the whole file".yellow());
+        }
+        if args.continue_download && args.connections > 1 {
+            eprintln!("{}", "Note: --continue resumes over a single connection; ignoring --connections".yellow());
+        }
+        
         let filename = downloader::extract_filename(&url);
-        let result = download_file(&url, Some(&filename), &config.user_agent, speed_unit).await?;
+        let result = download_file(&url, Some(&filename), &config.user_agent, speed_unit, download_options).await;
+        let record = history::HistoryRecord::new("Custom URL", &url, None, history::HistoryMode::Download, speed_unit);
+        history::save(&config, match &result {
+            Ok(result) => record.with_download(result),
+            Err(e) => record.with_error(e.as_ref()),
+        });
+        let result = result?;
         
         match output_format {
             OutputFormat::Json => {
@@ -120,13 +354,21 @@ async fn async_main(args: Args) -> Result<(), Box<dyn std::error::Error>> {
                 output::print_csv(&result, "Custom URL", &url, true);
             }
             OutputFormat::Human => {
-                ui::print_speed_only(
-                    result.status_code,
-                    result.total_time,
-                    result.bytes_downloaded,
-                );
+                if result.status_code == 416 && result.resumed_from.is_some() {
+                    println!("Already complete: {}", filename);
+                    return Ok(());
+                }
+                
+                ui::print_speed_only(&result);
+                ui::print_latency(&result);
+                
+                ui::print_stream_breakdown(&result.streams);
                 
-                if result.status_code == 200 {
+                if let Some(offset) = result.resumed_from {
+                    println!("Resumed from: {}", bytesize::ByteSize::b(offset));
+                }
+                
+                if result.status_code == 200 || result.status_code == 206 {
                     println!("Saved: {}", filename);
                 }
             }
@@ -135,40 +377,297 @@ async fn async_main(args: Args) -> Result<(), Box<dyn std::error::Error>> {
         return Ok(());
     }
     
-    // Determine mode: CLI flags override config
+    reprobe_quarantined(&server_data, &config, output_format).await;
+    
+    let home = match home_location(&config, args.near.as_deref()) {
+        Ok(home) => home,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    ui::set_home_location(home.clone());
+    
+    let settings = TestSettings {
+        config: &config,
+        speed_unit,
+        output_format,
+        download_options,
+        test_mode,
+    };
+    
+    if let Some(selector) = batch {
+        return run_batch_test(&settings, selector, args.parallel.unwrap_or(1) as usize).await;
+    }
+    
+    if menu {
+        // Interactive mode - show menu and loop
+        run_interactive_mode(&settings).await?;
+    } else {
+        // Non-interactive mode - run default server once
+        let pick = if args.auto {
+            ServerPick::Auto
+        } else if args.nearest.is_some() || args.near.is_some() {
+            let Some(home) = home else {
+                eprintln!("Error: --nearest needs a home location: set home_lat and home_lon in the config or use --near CITY");
+                std::process::exit(2);
+            };
+            ServerPick::Nearest(args.nearest.unwrap_or(1) as usize, home)
+        } else {
+            ServerPick::First
+        };
+        run_default_test(&settings, pick).await?;
+    }
+
+    Ok(())
+}
+
+// Whether to show the menu: CLI flags override the config. --auto and --nearest pick the
+// server themselves, so they always run once.
+fn opens_menu(args: &Args, config_interactive: bool) -> bool {
     let interactive_mode = if args.non_interactive {
         false
     } else if args.interactive {
         true
     } else {
-        config.interactive
+        config_interactive
     };
+    interactive_mode && !args.auto && args.nearest.is_none()
+}
+
+async fn run_default_test(settings: &TestSettings<'_>, pick: ServerPick) -> Result<(), Box<dyn std::error::Error>> {
+    let config = settings.config;
+    let human = settings.output_format == OutputFormat::Human;
     
-    if interactive_mode {
-        // Interactive mode - show menu and loop
-        run_interactive_mode(&config, speed_unit, output_format).await?;
-    } else {
-        // Non-interactive mode - run default server once
-        run_default_test(&config, speed_unit, output_format).await?;
+    // Load server data and get first server
+    let mut server_data = servers::load_local_server_data();
+    let server_list = servers::get_merged_server_list(&server_data, config);
+    
+    let candidates = match settings.test_mode {
+        TestMode::Download => server_list,
+        _ => upload_servers(&server_list),
+    };
+    
+    if candidates.is_empty() {
+        match settings.test_mode {
+            TestMode::Download => eprintln!("Error: No servers available"),
+            _ => eprintln!("Error: No upload-capable servers available"),
+        }
+        return Ok(());
+    }
+    
+    // Each target carries its distance from home when picked geographically
+    let targets: Vec<(servers::ServerMetadata, Option<f64>)> = match pick {
+        ServerPick::First => vec![(candidates[0].clone(), None)],
+        ServerPick::Auto => match select_closest_server(config, &mut server_data, &candidates, settings.output_format).await {
+            Some(server) => vec![(server, None)],
+            None => return Ok(()),
+        },
+        ServerPick::Nearest(count, home) => {
+            let ranked = geo::rank_by_distance(&candidates, &home);
+            if ranked.is_empty() {
+                eprintln!("Error: No servers have a known location");
+                return Ok(());
+            }
+            if human {
+                println!("{}", format!("Nearest servers to {}:", home.label).bright_black());
+            }
+            ranked.into_iter().take(count).map(|(s, km)| (s, Some(km))).collect()
+        }
+    };
+    
+    for (i, (server, distance)) in targets.iter().enumerate() {
+        if let (Some(km), true) = (distance, human) {
+            println!();
+            println!(
+                "{}",
+                format!("{} - {} ({:.0} km)", server.name, server.location.as_deref().unwrap_or("Unknown"), km).yellow()
+            );
+        }
+        
+        // Only the first CSV row gets a header so several servers form one table
+        let include_header = i == 0;
+        if matches!(settings.test_mode, TestMode::Download) {
+            run_download_test(settings, server, include_header).await?;
+        } else {
+            run_upload_test(settings, server, false, include_header).await?;
+        }
     }
 
     Ok(())
 }
 
-async fn run_default_test(config: &crate::config::Config, speed_unit: SpeedUnit, output_format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
-    // Load server data and get first server
+// Tests the selected servers one after another, or `parallel` at a time, and prints one
+// aggregated report. A failing server is reported and the batch carries on.
+async fn run_batch_test(settings: &TestSettings<'_>, selector: batch::Selector, parallel: usize) -> Result<(), Box<dyn std::error::Error>> {
+    use futures_util::StreamExt;
+    
+    let config = settings.config;
+    let human = settings.output_format == OutputFormat::Human;
+    
     let server_data = servers::load_local_server_data();
-    let server_list = servers::get_merged_server_list(&server_data);
+    let candidates = servers::get_merged_server_list(&server_data, config);
+    let full = servers::get_full_server_list(&server_data, config);
+    let mut targets = selector.select(&candidates, &full).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    });
     
-    if server_list.is_empty() {
-        eprintln!("Error: No servers available");
-        return Ok(());
+    if let TestMode::Upload(_) = settings.test_mode {
+        let total = targets.len();
+        targets = upload_servers(&targets);
+        if human && targets.len() < total {
+            println!("{}", format!("Skipping {} servers that do not accept uploads", total - targets.len()).bright_black());
+        }
+    }
+    if targets.is_empty() {
+        eprintln!("Error: No servers match the selection");
+        std::process::exit(2);
+    }
+    
+    let parallel = parallel.min(targets.len());
+    let start = std::time::Instant::now();
+    let entries = if parallel > 1 {
+        if human {
+            println!("{}", format!("Testing {} servers, {} at a time...", targets.len(), parallel).yellow());
+            println!(
+                "{}",
+                "Note: parallel tests share your connection, so each server's speed is its share of the link. Compare servers with each other, not with single tests.".bright_black()
+            );
+        }
+        
+        let multi = indicatif::MultiProgress::new();
+        if !human {
+            multi.set_draw_target(indicatif::ProgressDrawTarget::hidden());
+        }
+        let label_width = targets.iter().map(|s| s.name.chars().count()).max().unwrap_or(0).min(24);
+        let mut results: Vec<(usize, batch::BatchEntry)> = futures_util::stream::iter(targets.iter().enumerate())
+            .map(|(i, server)| {
+                let multi = &multi;
+                let label = format!("{:<width$.width$}", server.name, width = label_width);
+                async move {
+                    let entry = run_batch_entry(settings, server, downloader::Progress::Multi(multi.clone(), label), parallel).await;
+                    if human {
+                        let line = match (entry.speed_mbps, &entry.error) {
+                            (Some(mbps), _) => format!("{} {}  {:.2} Mbps", "✓".green(), server.name, mbps),
+                            (None, error) => format!("{} {}  {}", "✗".red(), server.name, error.as_deref().unwrap_or("failed")),
+                        };
+                        multi.suspend(|| println!("{}", line));
+                    }
+                    (i, entry)
+                }
+            })
+            .buffer_unordered(parallel)
+            .collect()
+            .await;
+        results.sort_by_key(|(i, _)| *i);
+        results.into_iter().map(|(_, entry)| entry).collect()
+    } else {
+        if human {
+            println!("{}", format!("Testing {} servers one after another...", targets.len()).yellow());
+        }
+        
+        let mut entries = Vec::with_capacity(targets.len());
+        for (i, server) in targets.iter().enumerate() {
+            if human {
+                println!();
+                println!(
+                    "{}",
+                    format!("[{}/{}] {} - {}", i + 1, targets.len(), server.name, server.location.as_deref().unwrap_or("Unknown")).cyan()
+                );
+            }
+            entries.push(run_batch_entry(settings, server, human.into(), 1).await);
+        }
+        entries
+    };
+    
+    let report = batch::BatchReport {
+        direction: if matches!(settings.test_mode, TestMode::Upload(_)) { "upload" } else { "download" },
+        parallel,
+        wall_time: start.elapsed().as_secs_f64(),
+        entries,
+    };
+    match settings.output_format {
+        OutputFormat::Json => output::print_batch_json(&report, false)?,
+        OutputFormat::JsonCompact => output::print_batch_json(&report, true)?,
+        OutputFormat::Csv => output::print_batch_csv(&report),
+        OutputFormat::Human => ui::print_batch_report(&report),
     }
     
-    let server = &server_list[0];
-    let result = download_file(&server.url, None, &config.user_agent, speed_unit).await?;
+    Ok(())
+}
+
+// One server of a batch. Results from parallel runs only count towards the server's
+// reachability and latency, since its speed was a share of the link.
+async fn run_batch_entry(settings: &TestSettings<'_>, server: &servers::ServerMetadata, progress: downloader::Progress, parallel: usize) -> batch::BatchEntry {
+    let config = settings.config;
+    let shared = parallel > 1;
+    let print = !shared && settings.output_format == OutputFormat::Human;
     
-    match output_format {
+    let entry = match settings.test_mode {
+        TestMode::Upload(upload_options) => {
+            let upload_url = server.upload_url.as_deref().unwrap_or(&server.url);
+            let result = upload_data(upload_url, server.upload_method(), &config.user_agent, settings.speed_unit, upload_options, progress).await;
+            let record = history_record(settings, server, history::HistoryMode::Upload).with_parallel(parallel);
+            match result {
+                Ok(result) => {
+                    history::save(config, record.with_upload(&result));
+                    if print {
+                        ui::print_upload_results(&result, false);
+                    }
+                    batch::BatchEntry::from_upload(server, &result)
+                }
+                Err(e) => {
+                    history::save(config, record.with_error(e.as_ref()));
+                    batch::BatchEntry::failed(server, e.as_ref())
+                }
+            }
+        }
+        _ => {
+            // Overlapping tests would probe each other's load, so parallel legs skip latency
+            let download_options = DownloadOptions { latency: settings.download_options.latency && !shared, ..settings.download_options };
+            let result = downloader::download_speed_test(&server.url, &config.user_agent, settings.speed_unit, download_options, progress).await;
+            let sample = match &result {
+                Ok(result) if shared => servers::HealthSample { speed_mbps: None, ..result.health_sample() },
+                Ok(result) => result.health_sample(),
+                Err(_) => servers::HealthSample::failure(),
+            };
+            if let Err(e) = servers::record_health(&server.url, sample) {
+                eprintln!("{}", format!("Warning: Failed to save server health: {}", e).yellow());
+            }
+            let record = history_record(settings, server, history::HistoryMode::Download).with_parallel(parallel);
+            history::save(config, match &result {
+                Ok(result) => record.with_download(result),
+                Err(e) => record.with_error(e.as_ref()),
+            });
+            match result {
+                Ok(result) => {
+                    if print {
+                        print_speed_only(&result);
+                    }
+                    batch::BatchEntry::from_download(server, &result)
+                }
+                Err(e) => batch::BatchEntry::failed(server, e.as_ref()),
+            }
+        }
+    };
+    if let (true, Some(error)) = (print, &entry.error) {
+        println!("{}", format!("✗ {}", error).red());
+    }
+    entry
+}
+
+async fn run_download_test(settings: &TestSettings<'_>, server: &servers::ServerMetadata, include_header: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let result = download_file(&server.url, None, &settings.config.user_agent, settings.speed_unit, settings.download_options).await;
+    record_health(&server.url, result.as_ref());
+    let record = history_record(settings, server, history::HistoryMode::Download);
+    history::save(settings.config, match &result {
+        Ok(result) => record.with_download(result),
+        Err(e) => record.with_error(e.as_ref()),
+    });
+    let result = result?;
+    
+    match settings.output_format {
         OutputFormat::Json => {
             output::print_json(&result, &server.name, &server.url, false)?;
         }
@@ -176,23 +675,24 @@ async fn run_default_test(config: &crate::config::Config, speed_unit: SpeedUnit,
             output::print_json(&result, &server.name, &server.url, true)?;
         }
         OutputFormat::Csv => {
-            output::print_csv(&result, &server.name, &server.url, true);
+            output::print_csv(&result, &server.name, &server.url, include_header);
         }
         OutputFormat::Human => {
-            print_speed_only(
-                result.status_code,
-                result.total_time,
-                result.bytes_downloaded,
-            );
+            print_speed_only(&result);
+            ui::print_latency(&result);
+            ui::print_stream_breakdown(&result.streams);
         }
     }
 
     Ok(())
 }
 
-async fn run_interactive_mode(config: &crate::config::Config, speed_unit: SpeedUnit, output_format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+async fn run_interactive_mode(settings: &TestSettings<'_>) -> Result<(), Box<dyn std::error::Error>> {
+    let config = settings.config;
+    let test_mode = settings.test_mode;
+    
     loop {
-        let selection = match show_menu() {
+        let selection = match show_menu(config) {
             Ok(sel) => sel,
             Err(_) => {
                 println!("\nExiting...");
@@ -200,16 +700,54 @@ async fn run_interactive_mode(config: &crate::config::Config, speed_unit: SpeedU
             }
         };
 
-        let (url, name, save_path): (String, String, Option<String>) = match selection {
+        if !matches!(test_mode, TestMode::Download) {
+            let server = match selection {
+                ServerSelection::Server(server) => server,
+                // A custom URL is taken as the upload endpoint, which has nothing to download
+                ServerSelection::Custom(url, _) if matches!(test_mode, TestMode::Upload(_)) => {
+                    servers::ServerMetadata::from(&config::CustomServer {
+                        name: "Custom URL".to_string(),
+                        url: url.clone(),
+                        upload_url: Some(url),
+                        ..Default::default()
+                    })
+                }
+                ServerSelection::Custom(..) => {
+                    println!("{}", "Custom URLs cannot be used with --bidir".yellow());
+                    println!();
+                    wait_for_continue().ok();
+                    continue;
+                }
+                ServerSelection::Quit => {
+                    println!("Exiting...");
+                    break;
+                }
+            };
+            
+            if server.upload_url.is_none() {
+                println!("{}", format!("{} does not accept uploads", server.name).yellow());
+            } else {
+                println!("{}", format!("Testing {}...", server.name).cyan());
+                run_upload_test(settings, &server, true, true).await?;
+            }
+            
+            println!();
+            wait_for_continue().ok();
+            continue;
+        }
+        
+        let (url, name, provider, save_path, listed): (String, String, Option<String>, Option<String>, bool) = match selection {
             ServerSelection::Server(server) => {
                 (
                     server.url.clone(),
                     server.name.clone(),
+                    server.provider.clone(),
                     None,
+                    true,
                 )
             }
             ServerSelection::Custom(url, save_path) => {
-                (url, "Custom URL".to_string(), save_path)
+                (url, "Custom URL".to_string(), None, save_path, false)
             }
             ServerSelection::Quit => {
                 println!("Exiting...");
@@ -219,9 +757,19 @@ async fn run_interactive_mode(config: &crate::config::Config, speed_unit: SpeedU
 
         print_download_header(&name, &save_path);
 
-        let result = download_file(&url, save_path.as_deref(), &config.user_agent, speed_unit).await?;
+        let result = download_file(&url, save_path.as_deref(), &config.user_agent, settings.speed_unit, settings.download_options).await;
+        // Only servers from the list have health entries to show in the menu
+        if listed {
+            record_health(&url, result.as_ref());
+        }
+        let record = history::HistoryRecord::new(&name, &url, provider.as_deref(), history::HistoryMode::Download, settings.speed_unit);
+        history::save(config, match &result {
+            Ok(result) => record.with_download(result),
+            Err(e) => record.with_error(e.as_ref()),
+        });
+        let result = result?;
 
-        match output_format {
+        match settings.output_format {
             OutputFormat::Json => {
                 output::print_json(&result, &name, &url, false)?;
             }
@@ -232,14 +780,8 @@ async fn run_interactive_mode(config: &crate::config::Config, speed_unit: SpeedU
                 output::print_csv(&result, &name, &url, true);
             }
             OutputFormat::Human => {
-                print_results(
-                    result.status_code,
-                    result.connect_time,
-                    result.ttfb,
-                    result.total_time,
-                    result.bytes_downloaded,
-                    save_path,
-                );
+                print_results(&result, save_path);
+                ui::print_stream_breakdown(&result.streams);
             }
         }
 
@@ -250,32 +792,280 @@ async fn run_interactive_mode(config: &crate::config::Config, speed_unit: SpeedU
     Ok(())
 }
 
-async fn update_server_list() -> Result<(), Box<dyn std::error::Error>> {
-    use colored::*;
+// Home for nearest-server ranking: a --near city, else home_lat/home_lon from the config
+fn home_location(config: &crate::config::Config, near: Option<&str>) -> Result<Option<geo::Location>, String> {
+    if let Some(query) = near {
+        return geo::find_city(query)
+            .map(|city| Some(geo::Location::from(city)))
+            .ok_or_else(|| format!("Unknown city '{}' for --near", query));
+    }
+    
+    Ok(match (config.home_lat, config.home_lon) {
+        (Some(lat), Some(lon)) => Some(geo::Location {
+            lat,
+            lon,
+            label: format!("{:.2}, {:.2}", lat, lon),
+        }),
+        _ => None,
+    })
+}
+
+// Servers that accept uploads, in list order
+fn upload_servers(server_list: &[servers::ServerMetadata]) -> Vec<servers::ServerMetadata> {
+    server_list
+        .iter()
+        .filter(|s| s.upload_url.is_some())
+        .cloned()
+        .collect()
+}
+
+// Picks the lowest-latency candidate for --auto and remembers it for the configured TTL
+async fn select_closest_server(config: &crate::config::Config, server_data: &mut servers::LocalServerData, candidates: &[servers::ServerMetadata], output_format: OutputFormat) -> Option<servers::ServerMetadata> {
+    let human = output_format == OutputFormat::Human;
+    let ttl = parse_duration(&config.auto_select_ttl).unwrap_or_else(|e| {
+        eprintln!("{}", format!("Warning: invalid auto_select_ttl ({}), using 24h", e).yellow());
+        std::time::Duration::from_secs(24 * 3600)
+    });
     
-    println!("{}", "Fetching remote server list...".yellow());
+    if let Some(server) = servers::cached_auto_selection(server_data, candidates, ttl) {
+        if human {
+            let latency = server_data.auto_selection.as_ref().map(|a| a.latency_ms).unwrap_or_default();
+            println!("{}", format!("Auto-selected: {} ({:.1} ms, cached)", server.name, latency).cyan());
+        }
+        return Some(server);
+    }
     
-    match servers::fetch_remote_server_list().await {
-        Ok(remote_list) => {
-            let count = remote_list.servers.len();
-            println!("{}", format!("✓ Downloaded {} servers (version {})", count, remote_list.version).green());
-            
-            let mut server_data = servers::load_local_server_data();
-            server_data.remote_list = Some(remote_list);
-            server_data.cache_timestamp = chrono::Utc::now();
-            
-            if let Err(e) = servers::save_local_server_data(&server_data) {
-                println!("{}", format!("Warning: Failed to save server list: {}", e).red());
-            } else {
-                println!("{}", "✓ Server list cached successfully".green());
+    if human {
+        println!("{}", format!("Probing {} servers for the lowest latency...", candidates.len()).bright_black());
+    }
+    
+    let Some(server) = servers::select_closest_server(server_data, candidates).await else {
+        eprintln!("Error: None of the servers answered the latency probe");
+        return None;
+    };
+    servers::save_local_server_data(server_data).ok();
+    
+    if human {
+        let latency = server_data.auto_selection.as_ref().map(|a| a.latency_ms).unwrap_or_default();
+        println!("{}", format!("Auto-selected: {} ({:.1} ms)", server.name, latency).cyan());
+    }
+    
+    Some(server)
+}
+
+// Runs an upload or bidirectional test against a server that accepts uploads.
+// `detailed` selects the interactive result layout, which always shows progress.
+async fn run_upload_test(settings: &TestSettings<'_>, server: &servers::ServerMetadata, detailed: bool, include_header: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let TestSettings { config, speed_unit, output_format, download_options, test_mode } = *settings;
+    let show_progress = detailed || output_format == OutputFormat::Human;
+    let upload_url = server.upload_url.as_deref().unwrap_or(&server.url);
+    
+    match test_mode {
+        TestMode::Upload(upload_options) => {
+            let result = upload_data(upload_url, server.upload_method(), &config.user_agent, speed_unit, upload_options, show_progress).await;
+            let record = history_record(settings, server, history::HistoryMode::Upload);
+            history::save(config, match &result {
+                Ok(result) => record.with_upload(result),
+                Err(e) => record.with_error(e.as_ref()),
+            });
+            print_upload_output(&result?, &server.name, upload_url, output_format, detailed, include_header)
+        }
+        TestMode::Bidir(mode, upload_options) => {
+            let result = run_bidir(server, &config.user_agent, speed_unit, mode, download_options, upload_options, show_progress).await;
+            record_health(&server.url, result.as_ref().map(|r| &r.download));
+            let record = history_record(settings, server, history::HistoryMode::Bidir);
+            history::save(config, match &result {
+                Ok(result) => record.with_download(&result.download).with_upload(&result.upload),
+                Err(e) => record.with_error(e.as_ref()),
+            });
+            let result = result?;
+            match output_format {
+                OutputFormat::Json => output::print_bidir_json(&result, &server.name, &server.url, false)?,
+                OutputFormat::JsonCompact => output::print_bidir_json(&result, &server.name, &server.url, true)?,
+                OutputFormat::Csv => output::print_bidir_csv(&result, &server.name, &server.url, include_header),
+                OutputFormat::Human => ui::print_bidir_results(&result, detailed),
             }
-            
             Ok(())
         }
-        Err(e) => {
-            println!("{}", format!("✗ Failed to fetch server list: {}", e).red());
-            println!("{}", "Using embedded fallback servers".yellow());
-            Err(e)
+        TestMode::Download => Ok(()),
+    }
+}
+
+fn print_upload_output(result: &uploader::UploadResult, name: &str, url: &str, output_format: OutputFormat, detailed: bool, include_header: bool) -> Result<(), Box<dyn std::error::Error>> {
+    match output_format {
+        OutputFormat::Json => output::print_upload_json(result, name, url, false)?,
+        OutputFormat::JsonCompact => output::print_upload_json(result, name, url, true)?,
+        OutputFormat::Csv => output::print_upload_csv(result, name, url, include_header),
+        OutputFormat::Human => ui::print_upload_results(result, detailed),
+    }
+    Ok(())
+}
+
+// Updates the server's persisted health with the outcome of a download test
+fn record_health<E>(url: &str, result: Result<&downloader::DownloadResult, E>) {
+    let sample = match result {
+        Ok(result) => result.health_sample(),
+        Err(_) => servers::HealthSample::failure(),
+    };
+    if let Err(e) = servers::record_health(url, sample) {
+        eprintln!("{}", format!("Warning: Failed to save server health: {}", e).yellow());
+    }
+}
+
+fn history_record(settings: &TestSettings<'_>, server: &servers::ServerMetadata, mode: history::HistoryMode) -> history::HistoryRecord {
+    history::HistoryRecord::new(&server.name, &server.url, server.provider.as_deref(), mode, settings.speed_unit)
+}
+
+// Gives quarantined servers whose cool-down has passed another chance before any server is picked
+async fn reprobe_quarantined(server_data: &servers::LocalServerData, config: &crate::config::Config, output_format: OutputFormat) {
+    let due = servers::quarantine_reprobe_due(server_data, config);
+    if due.is_empty() {
+        return;
+    }
+    
+    let human = output_format == OutputFormat::Human;
+    if human {
+        println!("{}", format!("Re-checking {} quarantined servers...", due.len()).bright_black());
+    }
+    let Ok(reports) = verify::verify_servers(&due, &config.user_agent, false).await else {
+        return;
+    };
+    
+    let samples: Vec<(String, servers::HealthSample)> = reports.iter()
+        .map(|r| (r.url.clone(), r.health_sample()))
+        .collect();
+    if let Err(e) = servers::record_health_samples(&samples) {
+        eprintln!("{}", format!("Warning: Failed to save server health: {}", e).yellow());
+    }
+    
+    if human {
+        for report in reports.iter().filter(|r| r.verdict != verify::Verdict::Failed) {
+            println!("{}", format!("✓ {} is reachable again", report.name).green());
+        }
+    }
+}
+
+// Probes the whole list, records each outcome as server health, and exits non-zero if any
+// server failed so the check can gate scripts
+async fn verify_server_list(server_data: &servers::LocalServerData, config: &crate::config::Config, output_format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let server_list = servers::get_full_server_list(server_data, config);
+    let human = output_format == OutputFormat::Human;
+    
+    if human {
+        println!("{}", format!("Verifying {} servers...", server_list.len()).yellow());
+    }
+    let reports = verify::verify_servers(&server_list, &config.user_agent, human).await?;
+    
+    let samples: Vec<(String, servers::HealthSample)> = reports.iter()
+        .map(|r| (r.url.clone(), r.health_sample()))
+        .collect();
+    if let Err(e) = servers::record_health_samples(&samples) {
+        eprintln!("{}", format!("Warning: Failed to save server health: {}", e).yellow());
+    }
+    
+    match output_format {
+        OutputFormat::Json => output::print_verify_json(&reports, false)?,
+        OutputFormat::JsonCompact => output::print_verify_json(&reports, true)?,
+        OutputFormat::Csv => output::print_verify_csv(&reports),
+        OutputFormat::Human => ui::print_verify_report(&reports),
+    }
+    
+    if reports.iter().any(|r| r.verdict == verify::Verdict::Failed) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// Explains entries a list could not contribute, most often because it was written for a newer speedo
+fn schema_note(update: &servers::SourceUpdate) -> Option<String> {
+    if update.schema > servers::SERVER_LIST_SCHEMA {
+        Some(format!(
+            "list uses schema {} but this speedo reads up to schema {}; {} entries skipped. Update speedo to use the full list",
+            update.schema, servers::SERVER_LIST_SCHEMA, update.skipped
+        ))
+    } else if update.skipped > 0 {
+        Some(format!("{} entries could not be read and were skipped", update.skipped))
+    } else {
+        None
+    }
+}
+
+async fn update_server_list(config: &crate::config::Config) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{}", "Fetching remote server lists...".yellow());
+    
+    let mut server_data = servers::load_local_server_data();
+    let outcomes = servers::refresh_server_sources(&mut server_data, config).await;
+    if outcomes.is_empty() {
+        println!("{}", "No remote sources configured in server_sources".yellow());
+        return Ok(());
+    }
+    
+    let mut first_error = None;
+    for (source, outcome) in outcomes {
+        match outcome {
+            Ok(update) => {
+                let mut line = format!("✓ {}: {} servers", source, update.servers);
+                if !update.version.is_empty() {
+                    line.push_str(&format!(" (version {})", update.version));
+                }
+                if update.not_modified {
+                    line.push_str(", not modified");
+                }
+                println!("{}", line.green());
+                if let Some(note) = schema_note(&update) {
+                    println!("{}", format!("  {}", note).yellow());
+                }
+            }
+            Err(e) => {
+                println!("{}", format!("✗ {}: {}", source, e).red());
+                let cached = match &source {
+                    sources::ServerSource::Url(url) => server_data.source_lists.contains_key(url),
+                    _ => server_data.remote_list.is_some(),
+                };
+                if cached {
+                    println!("{}", "  Keeping the cached list".yellow());
+                } else if source == sources::ServerSource::Default {
+                    println!("{}", "  Using embedded fallback servers".yellow());
+                }
+                first_error.get_or_insert(e);
+            }
         }
     }
+    
+    if let Err(e) = servers::save_local_server_data(&server_data) {
+        println!("{}", format!("Warning: Failed to save server list: {}", e).red());
+    } else {
+        println!("{}", "✓ Server lists cached successfully".green());
+    }
+    
+    first_error.map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn menu(flags: &[&str], config_interactive: bool) -> bool {
+        let args = Args::try_parse_from(std::iter::once("speedo").chain(flags.iter().copied())).unwrap();
+        opens_menu(&args, config_interactive)
+    }
+
+    #[test]
+    fn config_decides_without_flags() {
+        assert!(menu(&[], true));
+        assert!(!menu(&[], false));
+    }
+
+    #[test]
+    fn flags_override_config() {
+        assert!(menu(&["-i"], false));
+        assert!(!menu(&["-n"], true));
+    }
+
+    #[test]
+    fn auto_and_nearest_skip_the_menu() {
+        assert!(!menu(&["--auto"], true));
+        assert!(!menu(&["--nearest", "3"], true));
+        assert!(!menu(&["-n", "--auto"], false));
+    }
 }
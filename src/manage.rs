@@ -0,0 +1,364 @@
+// `speedo servers` subcommands for managing servers without hand-editing files.
+// Custom servers are added to and removed from the config file; enable/disable is stored as a
+// local override in the server data file, so it works for servers from any source.
+
+use clap::Subcommand;
+use colored::*;
+use serde::Serialize;
+use crate::config::{self, Config, CustomServer};
+use crate::servers::{self, HealthPolicy, LocalServerData, ServerHealth, ServerMetadata, Standing};
+use crate::validate::{self, Severity, ValidationReport};
+use std::path::PathBuf;
+
+#[derive(Subcommand, Debug)]
+pub enum ServersCommand {
+    /// List servers from every source, including disabled ones
+    List {
+        /// Only show servers in this region (e.g. Europe)
+        #[arg(long)]
+        region: Option<String>,
+
+        /// Only show servers from this provider (e.g. Hetzner)
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Output JSON instead of a table
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Show one server's details and health
+    Show {
+        /// Server name or URL
+        server: String,
+
+        /// Output JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Add a custom server to the config file
+    Add {
+        /// Display name
+        name: String,
+
+        /// URL of the test file
+        url: String,
+
+        /// Provider shown in the menu (default: Custom)
+        #[arg(long)]
+        provider: Option<String>,
+
+        /// Location shown in the menu (e.g. "Munich, DE")
+        #[arg(long)]
+        location: Option<String>,
+
+        /// Region used for grouping (e.g. Europe)
+        #[arg(long)]
+        region: Option<String>,
+
+        /// Latitude for --nearest
+        #[arg(long, allow_negative_numbers = true)]
+        lat: Option<f64>,
+
+        /// Longitude for --nearest
+        #[arg(long, allow_negative_numbers = true)]
+        lon: Option<f64>,
+
+        /// Size of the test file (e.g. 100MB), checked by --verify-servers
+        #[arg(long, value_parser = config::parse_size)]
+        file_size: Option<u64>,
+
+        /// Endpoint that accepts uploads, for --upload
+        #[arg(long)]
+        upload_url: Option<String>,
+
+        /// HTTP method for uploads (POST or PUT)
+        #[arg(long)]
+        upload_method: Option<String>,
+    },
+
+    /// Remove a custom server from the config file
+    Remove {
+        /// Server name or URL
+        server: String,
+    },
+
+    /// Enable a server that was disabled
+    Enable {
+        /// Server name or URL
+        server: String,
+    },
+
+    /// Disable a server so it is no longer offered or picked
+    Disable {
+        /// Server name or URL
+        server: String,
+    },
+
+    /// Check a server list file before publishing it; exits with status 1 on errors
+    Validate {
+        /// servers.json or a JSON/TOML fragment
+        file: PathBuf,
+
+        /// Skip the live HEAD requests that check reachability and file_size
+        #[arg(long)]
+        offline: bool,
+
+        /// Output JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Serialize)]
+struct ServerEntry<'a> {
+    #[serde(flatten)]
+    server: &'a ServerMetadata,
+    // Defined in [[custom_servers]] rather than a server list
+    custom: bool,
+    standing: Standing,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health: Option<&'a ServerHealth>,
+}
+
+pub async fn run(command: ServersCommand, config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = servers::load_local_server_data();
+    let all = servers::get_all_servers(&data, config);
+
+    match command {
+        ServersCommand::List { region, provider, json } => {
+            let matches = |value: &Option<String>, filter: &Option<String>| match filter {
+                Some(filter) => value.as_ref().is_some_and(|v| v.eq_ignore_ascii_case(filter)),
+                None => true,
+            };
+            let listed: Vec<&ServerMetadata> = all.iter()
+                .filter(|s| matches(&s.region, &region) && matches(&s.provider, &provider))
+                .collect();
+
+            if json {
+                let entries: Vec<ServerEntry> = listed.iter().map(|s| entry(s, &data, config, false)).collect();
+                println!("{}", serde_json::to_string_pretty(&entries)?);
+            } else {
+                print_server_table(&listed, config);
+            }
+        }
+        ServersCommand::Show { server, json } => {
+            let server = servers::find_server(&all, &server)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&entry(server, &data, config, true))?);
+            } else {
+                print_server_details(server, &data, config);
+            }
+        }
+        ServersCommand::Add { name, url, provider, location, region, lat, lon, file_size, upload_url, upload_method } => {
+            match reqwest::Url::parse(&url) {
+                Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+                _ => return Err(format!("'{}' is not an http(s) URL", url).into()),
+            }
+            if let Some(existing) = all.iter().find(|s| s.url == url) {
+                return Err(format!("{} is already listed as '{}'", url, existing.name).into());
+            }
+            if config.custom_servers.iter().any(|s| s.name.eq_ignore_ascii_case(&name)) {
+                return Err(format!("A custom server named '{}' already exists", name).into());
+            }
+
+            let path = config::add_custom_server(&CustomServer {
+                name: name.clone(),
+                url,
+                provider,
+                location,
+                region,
+                lat,
+                lon,
+                file_size,
+                upload_url,
+                upload_method,
+            })?;
+            println!("{}", format!("✓ Added '{}' to {}", name, path.display()).green());
+        }
+        ServersCommand::Remove { server } => {
+            let server = servers::find_server(&all, &server)?;
+            if !config.custom_servers.iter().any(|s| s.url == server.url) {
+                return Err(format!(
+                    "'{}' comes from a server list, not the config; use `speedo servers disable` to hide it",
+                    server.name
+                ).into());
+            }
+
+            let path = config::remove_custom_server(&server.url)?;
+            if data.enabled_overrides.remove(&server.url).is_some() {
+                servers::save_local_server_data(&data)?;
+            }
+            println!("{}", format!("✓ Removed '{}' from {}", server.name, path.display()).green());
+        }
+        ServersCommand::Enable { server } => set_enabled(&mut data, &all, &server, true)?,
+        ServersCommand::Disable { server } => set_enabled(&mut data, &all, &server, false)?,
+        ServersCommand::Validate { file, offline, json } => {
+            if !json {
+                let what = if offline { "Validating" } else { "Validating and probing servers in" };
+                println!("{}", format!("{} {}...", what, file.display()).yellow());
+            }
+            let report = validate::validate_file(&file, &config.user_agent, !offline, !json).await?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                print_validation_report(&report);
+            }
+            if report.errors > 0 {
+                std::process::exit(1);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn set_enabled(data: &mut LocalServerData, all: &[ServerMetadata], query: &str, enabled: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let server = servers::find_server(all, query)?;
+    data.enabled_overrides.insert(server.url.clone(), enabled);
+    servers::save_local_server_data(data)?;
+
+    let action = if enabled { "Enabled" } else { "Disabled" };
+    println!("{}", format!("✓ {} '{}'", action, server.name).green());
+    Ok(())
+}
+
+fn entry<'a>(server: &'a ServerMetadata, data: &'a LocalServerData, config: &Config, with_health: bool) -> ServerEntry<'a> {
+    ServerEntry {
+        server,
+        custom: is_custom(server, config),
+        standing: HealthPolicy::from_config(config).standing(data, &server.url),
+        health: data.health.get(&server.url).filter(|_| with_health),
+    }
+}
+
+fn is_custom(server: &ServerMetadata, config: &Config) -> bool {
+    config.custom_servers.iter().any(|c| c.url == server.url)
+}
+
+fn print_server_table(servers: &[&ServerMetadata], config: &Config) {
+    let width = |f: fn(&ServerMetadata) -> usize, min: usize| servers.iter().map(|s| f(s)).max().unwrap_or(0).max(min);
+    let name_width = width(|s| s.name.chars().count(), 4);
+    let provider_width = width(|s| s.provider.as_deref().unwrap_or("-").chars().count(), 8);
+    let region_width = width(|s| s.region.as_deref().unwrap_or("-").chars().count(), 6);
+
+    println!(
+        "{}",
+        format!(
+            "  {:<nw$}  {:<pw$}  {:<rw$}  Location",
+            "Name", "Provider", "Region",
+            nw = name_width, pw = provider_width, rw = region_width
+        ).bold()
+    );
+
+    for server in servers {
+        let line = format!(
+            "{} {:<nw$}  {:<pw$}  {:<rw$}  {}",
+            if server.enabled { "●" } else { "○" },
+            server.name,
+            server.provider.as_deref().unwrap_or("-"),
+            server.region.as_deref().unwrap_or("-"),
+            server.location.as_deref().unwrap_or("-"),
+            nw = name_width, pw = provider_width, rw = region_width
+        );
+        let tag = if is_custom(server, config) { " [custom]".cyan().to_string() } else { String::new() };
+        if server.enabled {
+            println!("{}{}", line, tag);
+        } else {
+            println!("{}{}", format!("{} (disabled)", line).bright_black(), tag);
+        }
+    }
+
+    let disabled = servers.iter().filter(|s| !s.enabled).count();
+    println!();
+    println!("{}", format!("{} servers ({} disabled)", servers.len(), disabled).bright_black());
+}
+
+fn print_server_details(server: &ServerMetadata, data: &LocalServerData, config: &Config) {
+    let unknown = "-".to_string();
+    println!("{}", server.name.bold());
+    println!("URL:       {}", server.url);
+    println!("Provider:  {}", server.provider.as_ref().unwrap_or(&unknown));
+    println!("Location:  {}", server.location.as_ref().unwrap_or(&unknown));
+    println!("Region:    {}", server.region.as_ref().unwrap_or(&unknown));
+    if let (Some(lat), Some(lon)) = (server.lat, server.lon) {
+        println!("Coords:    {:.4}, {:.4}", lat, lon);
+    }
+    if let Some(size) = server.file_size {
+        println!("File size: {}", bytesize::ByteSize::b(size));
+    }
+    if let Some(upload_url) = &server.upload_url {
+        println!("Upload:    {} ({})", upload_url, server.upload_method());
+    }
+    println!("Source:    {}", if is_custom(server, config) { "custom server (config)" } else { "server list" });
+
+    let overridden = data.enabled_overrides.contains_key(&server.url);
+    let state = match (server.enabled, overridden) {
+        (true, false) => "enabled".green(),
+        (true, true) => "enabled (local override)".green(),
+        (false, false) => "disabled".red(),
+        (false, true) => "disabled (local override)".red(),
+    };
+    println!("Status:    {}", state);
+
+    match data.health.get(&server.url) {
+        Some(health) => {
+            let standing = match health.standing(&HealthPolicy::from_config(config)) {
+                Standing::Healthy => "healthy".green(),
+                Standing::Demoted => "demoted".yellow(),
+                Standing::Quarantined => "quarantined".red(),
+            };
+            println!("Health:    {}", standing);
+            println!(
+                "{}",
+                format!(
+                    "  {} checks, {:.0}% success, {} failures in a row",
+                    health.total_checks,
+                    health.success_rate * 100.0,
+                    health.consecutive_failures
+                ).bright_black()
+            );
+            if health.avg_speed_mbps > 0.0 || health.avg_latency_ms > 0.0 {
+                println!(
+                    "{}",
+                    format!("  {:.1} MB/s avg, {:.1} ms avg latency", health.avg_speed_mbps / 8.0, health.avg_latency_ms).bright_black()
+                );
+            }
+            if let Some(checked) = health.last_checked {
+                println!("{}", format!("  Last checked {}", checked.format("%Y-%m-%d %H:%M UTC")).bright_black());
+            }
+        }
+        None => println!("Health:    {}", "not tested yet".bright_black()),
+    }
+}
+
+fn print_validation_report(report: &ValidationReport) {
+    // Findings are sorted by entry, so each entry gets one heading
+    let mut heading = None;
+    for finding in &report.findings {
+        if heading != Some(finding.index) {
+            heading = Some(finding.index);
+            match finding.index {
+                Some(index) => println!("{}", format!("servers[{}] {}", index, finding.name.as_deref().unwrap_or("")).trim_end().bold()),
+                None => println!("{}", "List".bold()),
+            }
+        }
+        match finding.severity {
+            Severity::Error => println!("  {} {}", "✗".red(), finding.message),
+            Severity::Warning => println!("  {} {}", "⚠".yellow(), finding.message),
+        }
+    }
+
+    if !report.findings.is_empty() {
+        println!();
+    }
+    let summary = format!("{} servers, {} errors, {} warnings", report.servers, report.errors, report.warnings);
+    if report.errors > 0 {
+        println!("{}", format!("✗ {}", summary).red().bold());
+    } else if report.warnings > 0 {
+        println!("{}", format!("✓ {}", summary).yellow());
+    } else {
+        println!("{}", format!("✓ {}", summary).green());
+    }
+}
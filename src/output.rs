@@ -2,7 +2,15 @@
 
 use chrono::Utc;
 use serde::Serialize;
-use crate::downloader::DownloadResult;
+use crate::batch::{BatchEntry, BatchReport};
+use crate::bidir::{Asymmetry, BidirMode, BidirResult};
+use crate::downloader::{DownloadResult, StreamResult, Warmup};
+use crate::history::HistoryRecord;
+use crate::latency::{Bufferbloat, Latency, LatencyStats};
+use crate::stats::{HistoryStats, Summary};
+use crate::timing::PhaseTimings;
+use crate::uploader::UploadResult;
+use crate::verify::VerifyReport;
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum OutputFormat {
@@ -36,20 +44,33 @@ struct ServerInfo {
 }
 
 #[derive(Serialize)]
-struct JsonOutput {
+struct JsonOutput<'a> {
     timestamp: String,
     server: ServerInfo,
-    results: JsonResults,
+    results: JsonResults<'a>,
 }
 
 #[derive(Serialize)]
-struct JsonResults {
+struct JsonResults<'a> {
     status_code: u16,
     bytes_downloaded: u64,
     total_time: f64,
     connect_time: f64,
     ttfb: f64,
+    timings: PhaseTimings,
     speed: SpeedInfo,
+    connections: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resumed_from: Option<u64>,
+    time_limited: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warmup: Option<Warmup>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    latency: Option<Latency>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bufferbloat: Option<Bufferbloat>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    streams: &'a [StreamResult],
 }
 
 pub fn print_json(
@@ -58,23 +79,13 @@ pub fn print_json(
     server_url: &str,
     compact: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let mbps = (result.bytes_downloaded as f64 * 8.0 / result.total_time) / 1_000_000.0;
-    let mb_s = (result.bytes_downloaded as f64 / result.total_time) / 1_000_000.0;
-
     let output = JsonOutput {
         timestamp: Utc::now().to_rfc3339(),
         server: ServerInfo {
             name: server_name.to_string(),
             url: server_url.to_string(),
         },
-        results: JsonResults {
-            status_code: result.status_code,
-            bytes_downloaded: result.bytes_downloaded,
-            total_time: result.total_time,
-            connect_time: result.connect_time,
-            ttfb: result.ttfb,
-            speed: SpeedInfo { mbps, mb_s },
-        },
+        results: download_results(result),
     };
 
     if compact {
@@ -86,21 +97,49 @@ pub fn print_json(
     Ok(())
 }
 
+fn download_results(result: &DownloadResult) -> JsonResults<'_> {
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
+    let mb_s = result.bytes_per_second() / 1_000_000.0;
+
+    JsonResults {
+        status_code: result.status_code,
+        bytes_downloaded: result.bytes_downloaded,
+        total_time: result.total_time,
+        connect_time: result.connect_time,
+        ttfb: result.ttfb,
+        timings: result.timings,
+        speed: SpeedInfo { mbps, mb_s },
+        connections: result.streams.len().max(1),
+        resumed_from: result.resumed_from,
+        time_limited: result.time_limited,
+        warmup: result.warmup,
+        latency: result.latency,
+        bufferbloat: result.latency.and_then(|l| l.bufferbloat()),
+        streams: &result.streams,
+    }
+}
+
 pub fn print_csv(
     result: &DownloadResult,
     server_name: &str,
     server_url: &str,
     include_header: bool,
 ) {
-    let mbps = (result.bytes_downloaded as f64 * 8.0 / result.total_time) / 1_000_000.0;
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
     let timestamp = Utc::now().to_rfc3339();
 
     if include_header {
-        println!("timestamp,server_name,server_url,bytes_downloaded,total_time,connect_time,ttfb,speed_mbps,status_code");
+        println!("timestamp,server_name,server_url,bytes_downloaded,total_time,connect_time,ttfb,speed_mbps,status_code,connections,dns,connect,tls,server_wait,transfer,idle_min_ms,idle_avg_ms,idle_max_ms,idle_jitter_ms,loaded_min_ms,loaded_avg_ms,loaded_max_ms,loaded_jitter_ms,latency_increase_ms,bufferbloat_grade");
     }
 
+    let latency = result.latency.unwrap_or_default();
+    let (increase, grade) = match latency.bufferbloat() {
+        Some(b) => (format!("{:.2}", b.latency_increase), b.grade.as_str()),
+        None => (String::new(), ""),
+    };
+
     println!(
-        "{},{},{},{},{:.3},{:.3},{:.3},{:.2},{}",
+        "{},{},{},{},{:.3},{:.3},{:.3},{:.2},{},{},{:.3},{:.3},{},{:.3},{:.3},{},{},{},{}",
         timestamp,
         escape_csv(server_name),
         escape_csv(server_url),
@@ -109,10 +148,421 @@ pub fn print_csv(
         result.connect_time,
         result.ttfb,
         mbps,
-        result.status_code
+        result.status_code,
+        result.streams.len().max(1),
+        result.timings.dns,
+        result.timings.connect,
+        result.timings.tls,
+        result.timings.server_wait,
+        result.timings.transfer,
+        latency_csv(latency.idle),
+        latency_csv(latency.loaded),
+        increase,
+        grade
     );
 }
 
+// min,avg,max,jitter columns, left empty when the probe did not run
+fn latency_csv(stats: Option<LatencyStats>) -> String {
+    match stats {
+        Some(s) => format!("{:.2},{:.2},{:.2},{:.2}", s.min, s.avg, s.max, s.jitter),
+        None => ",,,".to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct UploadJsonOutput {
+    timestamp: String,
+    server: ServerInfo,
+    results: UploadJsonResults,
+}
+
+#[derive(Serialize)]
+struct UploadJsonResults {
+    direction: &'static str,
+    status_code: u16,
+    bytes_uploaded: u64,
+    total_time: f64,
+    connect_time: f64,
+    ttfb: f64,
+    timings: PhaseTimings,
+    speed: SpeedInfo,
+    time_limited: bool,
+}
+
+pub fn print_upload_json(
+    result: &UploadResult,
+    server_name: &str,
+    server_url: &str,
+    compact: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = UploadJsonOutput {
+        timestamp: Utc::now().to_rfc3339(),
+        server: ServerInfo {
+            name: server_name.to_string(),
+            url: server_url.to_string(),
+        },
+        results: upload_results(result),
+    };
+
+    if compact {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+fn upload_results(result: &UploadResult) -> UploadJsonResults {
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
+    let mb_s = result.bytes_per_second() / 1_000_000.0;
+
+    UploadJsonResults {
+        direction: "upload",
+        status_code: result.status_code,
+        bytes_uploaded: result.bytes_uploaded,
+        total_time: result.total_time,
+        connect_time: result.connect_time,
+        ttfb: result.ttfb,
+        timings: result.timings,
+        speed: SpeedInfo { mbps, mb_s },
+        time_limited: result.time_limited,
+    }
+}
+
+pub fn print_upload_csv(
+    result: &UploadResult,
+    server_name: &str,
+    server_url: &str,
+    include_header: bool,
+) {
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
+    let timestamp = Utc::now().to_rfc3339();
+
+    if include_header {
+        println!("timestamp,server_name,server_url,bytes_uploaded,total_time,connect_time,ttfb,speed_mbps,status_code,dns,connect,tls,server_wait,transfer");
+    }
+
+    println!(
+        "{},{},{},{},{:.3},{:.3},{:.3},{:.2},{},{:.3},{:.3},{},{:.3},{:.3}",
+        timestamp,
+        escape_csv(server_name),
+        escape_csv(server_url),
+        result.bytes_uploaded,
+        result.total_time,
+        result.connect_time,
+        result.ttfb,
+        mbps,
+        result.status_code,
+        result.timings.dns,
+        result.timings.connect,
+        result.timings.tls,
+        result.timings.server_wait,
+        result.timings.transfer
+    );
+}
+
+#[derive(Serialize)]
+struct BidirJsonOutput<'a> {
+    timestamp: String,
+    server: ServerInfo,
+    mode: BidirMode,
+    download: JsonResults<'a>,
+    upload: UploadJsonResults,
+    asymmetry: Asymmetry,
+}
+
+pub fn print_bidir_json(
+    result: &BidirResult,
+    server_name: &str,
+    server_url: &str,
+    compact: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let output = BidirJsonOutput {
+        timestamp: Utc::now().to_rfc3339(),
+        server: ServerInfo {
+            name: server_name.to_string(),
+            url: server_url.to_string(),
+        },
+        mode: result.mode,
+        download: download_results(&result.download),
+        upload: upload_results(&result.upload),
+        asymmetry: result.asymmetry(),
+    };
+
+    if compact {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+pub fn print_bidir_csv(
+    result: &BidirResult,
+    server_name: &str,
+    server_url: &str,
+    include_header: bool,
+) {
+    let download_mbps = (result.download.bytes_per_second() * 8.0) / 1_000_000.0;
+    let upload_mbps = (result.upload.bytes_per_second() * 8.0) / 1_000_000.0;
+    let asymmetry = result.asymmetry();
+    let timestamp = Utc::now().to_rfc3339();
+
+    if include_header {
+        println!("timestamp,server_name,server_url,mode,download_mbps,upload_mbps,bytes_downloaded,bytes_uploaded,download_status,upload_status,asymmetry_ratio,asymmetric");
+    }
+
+    println!(
+        "{},{},{},{},{:.2},{:.2},{},{},{},{},{:.2},{}",
+        timestamp,
+        escape_csv(server_name),
+        escape_csv(server_url),
+        result.mode.as_str(),
+        download_mbps,
+        upload_mbps,
+        result.download.bytes_downloaded,
+        result.upload.bytes_uploaded,
+        result.download.status_code,
+        result.upload.status_code,
+        asymmetry.ratio,
+        asymmetry.asymmetric
+    );
+}
+
+#[derive(Serialize)]
+struct VerifyJsonOutput<'a> {
+    timestamp: String,
+    total: usize,
+    ok: usize,
+    warnings: usize,
+    failed: usize,
+    servers: &'a [VerifyReport],
+}
+
+pub fn print_verify_json(reports: &[VerifyReport], compact: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let count = |verdict| reports.iter().filter(|r| r.verdict == verdict).count();
+    let output = VerifyJsonOutput {
+        timestamp: Utc::now().to_rfc3339(),
+        total: reports.len(),
+        ok: count(crate::verify::Verdict::Ok),
+        warnings: count(crate::verify::Verdict::Warning),
+        failed: count(crate::verify::Verdict::Failed),
+        servers: reports,
+    };
+
+    if compact {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+pub fn print_verify_csv(reports: &[VerifyReport]) {
+    println!("server_name,server_url,verdict,status_code,method,response_ms,content_length,expected_size,redirects,final_url,issues");
+
+    for report in reports {
+        let optional = |value: Option<u64>| value.map(|v| v.to_string()).unwrap_or_default();
+        println!(
+            "{},{},{},{},{},{},{},{},{},{},{}",
+            escape_csv(&report.name),
+            escape_csv(&report.url),
+            report.verdict.as_str(),
+            report.status_code.map(|c| c.to_string()).unwrap_or_default(),
+            report.method,
+            report.response_ms.map(|ms| format!("{:.1}", ms)).unwrap_or_default(),
+            optional(report.content_length),
+            optional(report.expected_size),
+            report.redirects.len(),
+            escape_csv(report.redirects.last().map_or("", |s| s.as_str())),
+            escape_csv(&report.issues.join("; "))
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct BatchJsonOutput<'a> {
+    timestamp: String,
+    direction: &'static str,
+    parallel: usize,
+    wall_time: f64,
+    tested: usize,
+    failed: usize,
+    summary: BatchSummary<'a>,
+    servers: &'a [BatchEntry],
+}
+
+#[derive(Serialize)]
+struct BatchSummary<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best: Option<&'a BatchEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    worst: Option<&'a BatchEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    median_mbps: Option<f64>,
+    // Total throughput of overlapping tests, only for parallel runs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    aggregate_mbps: Option<f64>,
+}
+
+pub fn print_batch_json(report: &BatchReport, compact: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let output = BatchJsonOutput {
+        timestamp: Utc::now().to_rfc3339(),
+        direction: report.direction,
+        parallel: report.parallel,
+        wall_time: report.wall_time,
+        tested: report.entries.len(),
+        failed: report.failures(),
+        summary: BatchSummary {
+            best: report.best(),
+            worst: report.worst(),
+            median_mbps: report.median_mbps(),
+            aggregate_mbps: report.aggregate_mbps(),
+        },
+        servers: &report.entries,
+    };
+
+    if compact {
+        println!("{}", serde_json::to_string(&output)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    }
+
+    Ok(())
+}
+
+// One row per server, then best, worst and median rows (and aggregate for parallel runs);
+// `row` tells them apart
+pub fn print_batch_csv(report: &BatchReport) {
+    println!("row,direction,server_name,server_url,provider,location,speed_mbps,latency_ms,bytes,total_time,status_code,error");
+
+    let print_row = |row: &str, entry: &BatchEntry| {
+        println!(
+            "{},{},{},{},{},{},{},{},{},{:.3},{},{}",
+            row,
+            report.direction,
+            escape_csv(&entry.name),
+            escape_csv(&entry.url),
+            escape_csv(entry.provider.as_deref().unwrap_or("")),
+            escape_csv(entry.location.as_deref().unwrap_or("")),
+            entry.speed_mbps.map(|s| format!("{:.2}", s)).unwrap_or_default(),
+            entry.latency_ms.map(|ms| format!("{:.2}", ms)).unwrap_or_default(),
+            entry.bytes,
+            entry.total_time,
+            entry.status_code.map(|c| c.to_string()).unwrap_or_default(),
+            escape_csv(entry.error.as_deref().unwrap_or(""))
+        );
+    };
+
+    for entry in &report.entries {
+        print_row("server", entry);
+    }
+    if let Some(best) = report.best() {
+        print_row("best", best);
+    }
+    if let Some(worst) = report.worst() {
+        print_row("worst", worst);
+    }
+    if let Some(median) = report.median_mbps() {
+        println!("median,{},,,,,{:.2},,,,,", report.direction, median);
+    }
+    if let Some(aggregate) = report.aggregate_mbps() {
+        let bytes: u64 = report.entries.iter().filter(|e| e.speed_mbps.is_some()).map(|e| e.bytes).sum();
+        println!("aggregate,{},,,,,{:.2},,{},{:.3},,", report.direction, aggregate, bytes, report.wall_time);
+    }
+}
+
+// History is rendered to a string rather than printed, so `history export -o` can write it to a file
+pub fn history_json(records: &[HistoryRecord], compact: bool) -> Result<String, serde_json::Error> {
+    if compact {
+        serde_json::to_string(records)
+    } else {
+        serde_json::to_string_pretty(records)
+    }
+}
+
+pub fn history_csv(records: &[HistoryRecord]) -> String {
+    let mut csv = String::from("timestamp,server,url,provider,mode,status,speed_unit,download_mbps,upload_mbps,latency_ms,loaded_latency_ms,jitter_ms,bytes,total_time,connections,parallel,status_code,error\n");
+    let number = |value: Option<f64>| value.map(|v| format!("{:.2}", v)).unwrap_or_default();
+
+    for record in records {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{:.3},{},{},{},{}\n",
+            record.timestamp.to_rfc3339(),
+            escape_csv(&record.server),
+            escape_csv(&record.url),
+            escape_csv(record.provider.as_deref().unwrap_or("")),
+            record.mode.as_str(),
+            record.status.as_str(),
+            record.speed_unit,
+            number(record.download_mbps),
+            number(record.upload_mbps),
+            number(record.latency_ms),
+            number(record.loaded_latency_ms),
+            number(record.jitter_ms),
+            record.bytes,
+            record.total_time,
+            record.connections,
+            record.parallel.map(|p| p.to_string()).unwrap_or_default(),
+            record.status_code.map(|c| c.to_string()).unwrap_or_default(),
+            escape_csv(record.error.as_deref().unwrap_or(""))
+        ));
+    }
+    csv
+}
+
+pub fn print_history_stats_json(stats: &HistoryStats, compact: bool) -> Result<(), Box<dyn std::error::Error>> {
+    if compact {
+        println!("{}", serde_json::to_string(stats)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(stats)?);
+    }
+    Ok(())
+}
+
+// One `server` row per server and direction, then one `hour` row per hour of day with runs.
+// Trend columns are only filled on server rows.
+pub fn print_history_stats_csv(stats: &HistoryStats) {
+    println!("row,direction,server_name,server_url,hour,runs,failed,samples,min_mbps,max_mbps,mean_mbps,median_mbps,p95_mbps,stddev_mbps,this_week_median_mbps,last_week_median_mbps,week_over_week_pct,vs_period_median_pct");
+
+    let number = |value: Option<f64>| value.map(|v| format!("{:.2}", v)).unwrap_or_default();
+    let summary = |s: Option<Summary>| match s {
+        Some(s) => format!("{},{:.2},{:.2},{:.2},{:.2},{:.2},{:.2}", s.count, s.min, s.max, s.mean, s.median, s.p95, s.stddev),
+        None => "0,,,,,,".to_string(),
+    };
+
+    for server in &stats.servers {
+        let trend = server.trend.as_ref();
+        println!(
+            "server,{},{},{},,{},{},{},{},{},{},{}",
+            server.direction.as_str(),
+            escape_csv(&server.server),
+            escape_csv(&server.url),
+            server.runs,
+            server.failed,
+            summary(server.speed_mbps),
+            number(trend.map(|t| t.this_week_median)),
+            number(trend.and_then(|t| t.last_week_median)),
+            number(trend.and_then(|t| t.week_over_week_pct)),
+            number(trend.and_then(|t| t.vs_period_median_pct))
+        );
+    }
+    for hour in &stats.hours {
+        println!(
+            "hour,{},,,{},{},{},{},,,,",
+            hour.direction.as_str(),
+            hour.hour,
+            hour.runs,
+            hour.failed,
+            summary(Some(hour.speed_mbps))
+        );
+    }
+}
+
 fn escape_csv(s: &str) -> String {
     if s.contains(',') || s.contains('"') || s.contains('\n') {
         format!("\"{}\"", s.replace('"', "\"\""))
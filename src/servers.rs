@@ -2,8 +2,11 @@
 // Contains test file URLs from Cloudflare, Tele2, Hetzner, and Vultr.
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 use chrono::{DateTime, Utc};
+use crate::config::{Config, CustomServer};
+use crate::sources::{self, Fetched, ServerSource, Validators};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerMetadata {
@@ -21,8 +24,43 @@ pub struct ServerMetadata {
     pub lon: Option<f64>,
     #[serde(default)]
     pub file_size: Option<u64>,
-    #[serde(default)]
+    // Entries in hand-written fragments are enabled unless they say otherwise
+    #[serde(default = "default_enabled")]
     pub enabled: bool,
+    // Endpoint that accepts uploads; servers without one are download-only
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_url: Option<String>,
+    // HTTP method for uploads, POST when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upload_method: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl ServerMetadata {
+    pub fn upload_method(&self) -> &str {
+        self.upload_method.as_deref().unwrap_or("POST")
+    }
+}
+
+impl From<&CustomServer> for ServerMetadata {
+    fn from(custom: &CustomServer) -> Self {
+        ServerMetadata {
+            name: custom.name.clone(),
+            url: custom.url.clone(),
+            provider: Some(custom.provider.clone().unwrap_or_else(|| "Custom".to_string())),
+            location: custom.location.clone(),
+            region: custom.region.clone(),
+            lat: custom.lat,
+            lon: custom.lon,
+            file_size: custom.file_size,
+            enabled: true,
+            upload_url: custom.upload_url.clone(),
+            upload_method: custom.upload_method.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -40,16 +78,162 @@ pub struct ServerHealth {
     pub failures: u32,
     #[serde(default)]
     pub total_checks: u32,
+    // Failures since the last success; decides quarantine
+    #[serde(default)]
+    pub consecutive_failures: u32,
     #[serde(default)]
     pub user_rating: Option<i32>, // -1 to 5 stars, None = not rated
     #[serde(default)]
     pub user_notes: Option<String>,
 }
 
+impl ServerHealth {
+    fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            last_checked: None,
+            success_rate: 0.0,
+            avg_speed_mbps: 0.0,
+            avg_latency_ms: 0.0,
+            failures: 0,
+            total_checks: 0,
+            consecutive_failures: 0,
+            user_rating: None,
+            user_notes: None,
+        }
+    }
+
+    // Folds one test outcome into the counters and rolling averages
+    pub fn record(&mut self, sample: &HealthSample) {
+        let previous_successes = self.total_checks - self.failures;
+
+        self.total_checks += 1;
+        self.last_checked = Some(Utc::now());
+
+        if sample.success {
+            self.consecutive_failures = 0;
+            if let Some(speed) = sample.speed_mbps {
+                self.avg_speed_mbps = rolling_average(self.avg_speed_mbps, speed, previous_successes);
+            }
+            if let Some(latency) = sample.latency_ms {
+                self.avg_latency_ms = rolling_average(self.avg_latency_ms, latency, previous_successes);
+            }
+        } else {
+            self.failures += 1;
+            self.consecutive_failures += 1;
+        }
+
+        self.success_rate = (self.total_checks - self.failures) as f64 / self.total_checks as f64;
+    }
+
+    pub fn standing(&self, policy: &HealthPolicy) -> Standing {
+        if policy.quarantine_after_failures > 0 && self.consecutive_failures >= policy.quarantine_after_failures {
+            Standing::Quarantined
+        } else if self.total_checks >= DEMOTE_MIN_CHECKS && self.success_rate < policy.demote_below_success_rate {
+            Standing::Demoted
+        } else {
+            Standing::Healthy
+        }
+    }
+
+    // A quarantined server gets another chance once the cool-down since its last failure is over
+    pub fn reprobe_due(&self, policy: &HealthPolicy) -> bool {
+        self.standing(policy) == Standing::Quarantined
+            && self.last_checked.is_none_or(|checked| Utc::now().signed_duration_since(checked) >= policy.cooldown)
+    }
+}
+
+// Ordered from most to least preferred, so sorting by standing de-prioritises weak servers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Standing {
+    Healthy,
+    // Reliable enough to offer, but listed after healthy servers
+    Demoted,
+    // Failed repeatedly; left out of automatic selection until a re-probe succeeds
+    Quarantined,
+}
+
+// Health thresholds from the config
+#[derive(Debug, Clone, Copy)]
+pub struct HealthPolicy {
+    pub quarantine_after_failures: u32,
+    pub demote_below_success_rate: f64,
+    pub cooldown: chrono::Duration,
+}
+
+impl HealthPolicy {
+    pub fn from_config(config: &Config) -> Self {
+        let cooldown = crate::config::parse_duration(&config.quarantine_cooldown)
+            .ok()
+            .and_then(|d| chrono::Duration::from_std(d).ok())
+            .unwrap_or_else(|| chrono::Duration::hours(6));
+
+        Self {
+            quarantine_after_failures: config.quarantine_after_failures,
+            demote_below_success_rate: config.demote_below_success_rate,
+            cooldown,
+        }
+    }
+
+    pub fn standing(&self, data: &LocalServerData, url: &str) -> Standing {
+        data.health.get(url).map_or(Standing::Healthy, |h| h.standing(self))
+    }
+}
+
+// Success rate is too noisy to act on before a server has been checked this many times
+const DEMOTE_MIN_CHECKS: u32 = 3;
+
+// Weight of the newest sample, so the averages follow a server that got faster or slower
+const HEALTH_SMOOTHING: f64 = 0.3;
+
+fn rolling_average(current: f64, sample: f64, previous_samples: u32) -> f64 {
+    if previous_samples == 0 || current <= 0.0 {
+        sample
+    } else {
+        current + HEALTH_SMOOTHING * (sample - current)
+    }
+}
+
+// Outcome of one test against a server, as recorded in its health entry
+#[derive(Debug, Clone, Copy)]
+pub struct HealthSample {
+    pub success: bool,
+    // None for checks that do not measure throughput, such as --verify-servers probes
+    pub speed_mbps: Option<f64>,
+    pub latency_ms: Option<f64>,
+}
+
+impl HealthSample {
+    pub fn failure() -> Self {
+        Self {
+            success: false,
+            speed_mbps: None,
+            latency_ms: None,
+        }
+    }
+}
+
+// Newest server list schema this build understands. Lists with a higher schema are still
+// read, but entries this build cannot parse are skipped and the user is told to upgrade.
+pub const SERVER_LIST_SCHEMA: u32 = 1;
+
+fn default_schema() -> u32 {
+    1
+}
+
+// Fragments from local sources may leave out everything but `servers`
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerList {
+    // Format of the file, bumped on incompatible changes; lists from before it existed are 1
+    #[serde(default = "default_schema")]
+    pub schema: u32,
+    // Version of the list's contents
+    #[serde(default)]
     pub version: String,
+    #[serde(default)]
     pub updated: DateTime<Utc>,
+    #[serde(default)]
     pub servers: Vec<ServerMetadata>,
 }
 
@@ -58,6 +242,59 @@ pub struct LocalServerData {
     pub health: std::collections::HashMap<String, ServerHealth>,
     pub cache_timestamp: DateTime<Utc>,
     pub remote_list: Option<ServerList>,
+    // Lists fetched from the other URL sources in `server_sources`, keyed by URL
+    #[serde(default)]
+    pub source_lists: std::collections::HashMap<String, ServerList>,
+    // ETag / Last-Modified of each fetched list, keyed by URL, for conditional requests
+    #[serde(default)]
+    pub source_validators: std::collections::HashMap<String, Validators>,
+    #[serde(default)]
+    pub auto_selection: Option<AutoSelection>,
+    // Local `speedo servers enable/disable` choices, keyed by server URL, that win over `enabled`
+    #[serde(default)]
+    pub enabled_overrides: std::collections::HashMap<String, bool>,
+}
+
+impl Default for ServerList {
+    fn default() -> Self {
+        Self {
+            schema: default_schema(),
+            version: String::new(),
+            updated: DateTime::default(),
+            servers: Vec::new(),
+        }
+    }
+}
+
+impl ServerList {
+    // Reads a list one entry at a time, so an entry written for a newer speedo (or a broken
+    // one) is skipped instead of failing the whole list. Returns the list and the skip count.
+    pub fn from_value(value: serde_json::Value) -> Result<(Self, usize), Box<dyn std::error::Error>> {
+        let serde_json::Value::Object(mut fields) = value else {
+            return Err("server list is not an object".into());
+        };
+        
+        let entries = match fields.remove("servers") {
+            Some(serde_json::Value::Array(entries)) => entries,
+            Some(_) => return Err("`servers` is not a list".into()),
+            None => Vec::new(),
+        };
+        let total = entries.len();
+        let servers: Vec<ServerMetadata> = entries
+            .into_iter()
+            .filter_map(|entry| serde_json::from_value(entry).ok())
+            .collect();
+        
+        let list = ServerList {
+            // A schema too large for u32 is still newer than anything this build reads
+            schema: fields.get("schema").and_then(|v| v.as_u64()).map_or(default_schema(), |s| u32::try_from(s).unwrap_or(u32::MAX)),
+            version: fields.get("version").and_then(|v| v.as_str()).unwrap_or_default().to_string(),
+            updated: fields.remove("updated").and_then(|v| serde_json::from_value(v).ok()).unwrap_or_default(),
+            servers,
+        };
+        let skipped = total - list.servers.len();
+        Ok((list, skipped))
+    }
 }
 
 impl Default for LocalServerData {
@@ -66,13 +303,27 @@ impl Default for LocalServerData {
             health: std::collections::HashMap::new(),
             cache_timestamp: Utc::now(),
             remote_list: None,
+            source_lists: std::collections::HashMap::new(),
+            source_validators: std::collections::HashMap::new(),
+            auto_selection: None,
+            enabled_overrides: std::collections::HashMap::new(),
         }
     }
 }
 
+// Server picked by --auto, reused until it is older than the configured TTL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoSelection {
+    pub url: String,
+    pub name: String,
+    pub latency_ms: f64,
+    pub selected_at: DateTime<Utc>,
+}
+
 pub struct TestServer {
     pub name: &'static str,
     pub url: &'static str,
+    pub upload_url: Option<&'static str>,
 }
 
 // Embedded fallback servers (used if remote fetch fails)
@@ -80,51 +331,66 @@ pub const SERVERS: &[TestServer] = &[
     TestServer {
         name: "Cloudflare (CDN)",
         url: "https://speed.cloudflare.com/__down?bytes=100000000",
+        upload_url: Some("https://speed.cloudflare.com/__up"),
     },
     TestServer {
         name: "Tele2 (Global)",
         url: "http://speedtest.tele2.net/100MB.zip",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Nuremberg)",
         url: "https://nbg1-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Falkenstein)",
         url: "https://fsn1-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Helsinki)",
         url: "https://hel1-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Ashburn VA)",
         url: "https://ash-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Hillsboro OR)",
         url: "https://hil-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Hetzner (Singapore)",
         url: "https://sin-speed.hetzner.com/100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Vultr (New Jersey)",
         url: "https://nj-us-ping.vultr.com/vultr.com.100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Vultr (Silicon Valley)",
         url: "https://sjo-ca-us-ping.vultr.com/vultr.com.100MB.bin",
+        upload_url: None,
     },
     TestServer {
         name: "Vultr (Singapore)",
         url: "https://sgp-ping.vultr.com/vultr.com.100MB.bin",
+        upload_url: None,
     },
 ];
 
 const REMOTE_SERVER_LIST_URL: &str = "https://raw.githubusercontent.com/coryzibell/speedo/main/servers.json";
-const CACHE_EXPIRY_DAYS: i64 = 7;
+const REMOTE_SERVER_LIST_SIGNATURE_URL: &str = "https://raw.githubusercontent.com/coryzibell/speedo/main/servers.json.minisig";
+// minisign public key that servers.json is signed with. None until the maintainer embeds
+// theirs (see RELEASING in the README); without a key the list is used unsigned.
+const SERVER_LIST_PUBLIC_KEY: Option<&str> = None;
+const AUTO_PROBE_CONCURRENCY: usize = 16;
 
 fn get_server_data_path() -> PathBuf {
     if let Some(data_dir) = dirs::data_local_dir() {
@@ -156,48 +422,427 @@ pub fn save_local_server_data(data: &LocalServerData) -> std::io::Result<()> {
     Ok(())
 }
 
-pub async fn fetch_remote_server_list() -> Result<ServerList, Box<dyn std::error::Error>> {
-    let client = reqwest::Client::builder()
-        .user_agent("speedo")
-        .timeout(std::time::Duration::from_secs(10))
-        .build()?;
+// Fetches the public list, or None when the cached copy is still current. A 304 needs no
+// signature check because the cached copy was checked when it was fetched.
+pub async fn fetch_remote_server_list(validators: Option<&Validators>) -> Result<Option<(ServerList, usize, Validators)>, Box<dyn std::error::Error>> {
+    let client = sources::client()?;
     
-    let response = client.get(REMOTE_SERVER_LIST_URL).send().await?;
-    let list = response.json::<ServerList>().await?;
-    Ok(list)
+    let (body, validators) = match sources::conditional_get(&client, REMOTE_SERVER_LIST_URL, validators).await? {
+        Fetched::NotModified => return Ok(None),
+        Fetched::Body(body, validators) => (body, validators),
+    };
+    // The list decides which URLs get fetched, so nothing in it is used before the signature checks out
+    if let Some(key) = SERVER_LIST_PUBLIC_KEY {
+        let signature = match client.get(REMOTE_SERVER_LIST_SIGNATURE_URL).send().await?.error_for_status() {
+            Ok(response) => response.text().await?,
+            Err(e) => return Err(UntrustedServerList(format!("signature could not be downloaded ({})", e)).into()),
+        };
+        verify_server_list(key, &body, &signature)?;
+    }
+    let (list, skipped) = ServerList::from_value(serde_json::from_slice(&body)?)?;
+    Ok(Some((list, skipped, validators)))
+}
+
+// A downloaded server list that is unsigned or does not match its signature
+#[derive(Debug)]
+pub struct UntrustedServerList(String);
+
+impl std::fmt::Display for UntrustedServerList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "server list rejected: {}", self.0)
+    }
+}
+
+impl std::error::Error for UntrustedServerList {}
+
+pub fn verify_server_list(key: &str, body: &[u8], signature: &str) -> Result<(), UntrustedServerList> {
+    let key = minisign_verify::PublicKey::from_base64(key)
+        .map_err(|e| UntrustedServerList(format!("invalid embedded public key ({})", e)))?;
+    let signature = minisign_verify::Signature::decode(signature)
+        .map_err(|e| UntrustedServerList(format!("malformed signature ({})", e)))?;
+    key.verify(body, &signature, false)
+        .map_err(|_| UntrustedServerList("signature does not match the list".to_string()))
+}
+
+pub fn should_update_cache(data: &LocalServerData, ttl: std::time::Duration) -> bool {
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+    Utc::now().signed_duration_since(data.cache_timestamp) >= ttl
+}
+
+// Servers for automatic selection: quarantined servers are left out and demoted ones come last
+pub fn get_merged_server_list(data: &LocalServerData, config: &Config) -> Vec<ServerMetadata> {
+    let policy = HealthPolicy::from_config(config);
+    get_full_server_list(data, config)
+        .into_iter()
+        .filter(|s| policy.standing(data, &s.url) != Standing::Quarantined)
+        .collect()
 }
 
-pub fn should_update_cache(data: &LocalServerData) -> bool {
-    let now = Utc::now();
-    let elapsed = now.signed_duration_since(data.cache_timestamp);
-    elapsed.num_days() >= CACHE_EXPIRY_DAYS
+// Every enabled server, including quarantined ones, ordered by standing. Used where the
+// user picks or checks servers themselves.
+pub fn get_full_server_list(data: &LocalServerData, config: &Config) -> Vec<ServerMetadata> {
+    // Filter out disabled servers and apply health data
+    let policy = HealthPolicy::from_config(config);
+    let mut servers: Vec<ServerMetadata> = get_all_servers(data, config).into_iter()
+        .filter(|s| s.enabled)
+        .collect();
+    servers.sort_by_key(|s| policy.standing(data, &s.url));
+    servers
 }
 
-pub fn get_merged_server_list(data: &LocalServerData) -> Vec<ServerMetadata> {
-    let mut servers = Vec::new();
+// Every server from every source, disabled ones included, with local enable/disable overrides applied
+pub fn get_all_servers(data: &LocalServerData, config: &Config) -> Vec<ServerMetadata> {
+    let mut servers: Vec<ServerMetadata> = Vec::new();
     
-    // Start with remote servers if available
-    if let Some(ref remote_list) = data.remote_list {
-        servers.extend(remote_list.servers.clone());
-    } else {
-        // Fallback to embedded servers
-        for server in SERVERS {
-            servers.push(ServerMetadata {
-                name: server.name.to_string(),
-                url: server.url.to_string(),
-                provider: None,
-                location: None,
-                region: None,
-                lat: None,
-                lon: None,
-                file_size: Some(100_000_000),
-                enabled: true,
-            });
+    // Sources in configured order; the first source to list a URL wins
+    for source in sources::configured_sources(config) {
+        let listed = match &source {
+            // Start with remote servers if available, else the embedded fallback
+            ServerSource::Default => match data.remote_list {
+                Some(ref remote_list) => remote_list.servers.clone(),
+                None => embedded_servers(),
+            },
+            ServerSource::Url(url) => data.source_lists.get(url).map(|l| l.servers.clone()).unwrap_or_default(),
+            ServerSource::Path(path) => path_source_servers(path),
+        };
+        
+        for server in listed {
+            if !servers.iter().any(|s| s.url == server.url) {
+                servers.push(server);
+            }
         }
     }
     
-    // Filter out disabled servers and apply health data
-    servers.into_iter()
-        .filter(|s| s.enabled)
+    // Nothing usable from any source: better the embedded servers than an empty menu
+    if servers.is_empty() {
+        servers = embedded_servers();
+    }
+    
+    // Custom servers from the config come after the list; a URL already listed is not repeated
+    for custom in &config.custom_servers {
+        if !servers.iter().any(|s| s.url == custom.url) {
+            servers.push(ServerMetadata::from(custom));
+        }
+    }
+    
+    for server in &mut servers {
+        if let Some(&enabled) = data.enabled_overrides.get(&server.url) {
+            server.enabled = enabled;
+        }
+    }
+    servers
+}
+
+// Local sources are read once per run, so a list built several times warns about them once
+fn path_source_servers(path: &Path) -> Vec<ServerMetadata> {
+    static LOADED: OnceLock<Mutex<std::collections::HashMap<PathBuf, Vec<ServerMetadata>>>> = OnceLock::new();
+    let mut loaded = LOADED.get_or_init(Default::default).lock().unwrap_or_else(|e| e.into_inner());
+    loaded.entry(path.to_path_buf())
+        .or_insert_with(|| match sources::load_path_source(path) {
+            Ok((list, skipped)) => {
+                if skipped > 0 {
+                    eprintln!("Warning: Skipped {} unreadable entries in server source {}", skipped, path.display());
+                }
+                list.servers
+            }
+            Err(e) => {
+                eprintln!("Warning: Skipping server source {}: {}", path.display(), e);
+                Vec::new()
+            }
+        })
+        .clone()
+}
+
+// Looks a server up by exact URL, or by name compared without case. A name shared by several
+// servers is refused rather than guessed.
+pub fn find_server<'a>(servers: &'a [ServerMetadata], query: &str) -> Result<&'a ServerMetadata, String> {
+    if let Some(server) = servers.iter().find(|s| s.url == query) {
+        return Ok(server);
+    }
+
+    let named: Vec<&ServerMetadata> = servers.iter()
+        .filter(|s| s.name.eq_ignore_ascii_case(query.trim()))
+        .collect();
+    match named.as_slice() {
+        [] => Err(format!("No server named '{}' (see `speedo servers list`)", query)),
+        [server] => Ok(server),
+        several => Err(format!(
+            "'{}' matches {} servers; use the URL instead:\n{}",
+            query,
+            several.len(),
+            several.iter().map(|s| format!("  {}", s.url)).collect::<Vec<_>>().join("\n")
+        )),
+    }
+}
+
+fn embedded_servers() -> Vec<ServerMetadata> {
+    SERVERS.iter().map(|server| ServerMetadata {
+        name: server.name.to_string(),
+        url: server.url.to_string(),
+        provider: None,
+        location: None,
+        region: None,
+        lat: None,
+        lon: None,
+        file_size: Some(100_000_000),
+        enabled: true,
+        upload_url: server.upload_url.map(|u| u.to_string()),
+        upload_method: None,
+    }).collect()
+}
+
+// What refreshing one source produced, for reporting
+pub struct SourceUpdate {
+    pub servers: usize,
+    pub version: String,
+    pub schema: u32,
+    // Entries left out because this build could not read them
+    pub skipped: usize,
+    // The server answered 304 and the cached copy was kept
+    pub not_modified: bool,
+}
+
+// Fetches every URL source into the cache with conditional requests, returning each one's
+// outcome so callers can report it. Local paths are read fresh on every run instead.
+pub async fn refresh_server_sources(data: &mut LocalServerData, config: &Config) -> Vec<(ServerSource, Result<SourceUpdate, Box<dyn std::error::Error>>)> {
+    let mut outcomes = Vec::new();
+    let sources = sources::configured_sources(config);
+    
+    for source in &sources {
+        let url = match source {
+            ServerSource::Default => REMOTE_SERVER_LIST_URL,
+            ServerSource::Url(url) => url.as_str(),
+            ServerSource::Path(_) => continue,
+        };
+        // Without a cached copy a 304 would leave nothing to use, so only ask conditionally with one
+        let validators = match cached_list(data, source) {
+            Some(_) => data.source_validators.get(url).cloned(),
+            None => None,
+        };
+        
+        let fetched = match source {
+            ServerSource::Default => fetch_remote_server_list(validators.as_ref()).await,
+            _ => sources::fetch_url_source(url, validators.as_ref()).await,
+        };
+        
+        let outcome = fetched.map(|fetched| match fetched {
+            Some((list, skipped, validators)) => {
+                let update = SourceUpdate {
+                    servers: list.servers.len(),
+                    version: list.version.clone(),
+                    schema: list.schema,
+                    skipped,
+                    not_modified: false,
+                };
+                data.source_validators.insert(url.to_string(), validators);
+                match source {
+                    ServerSource::Url(url) => {
+                        data.source_lists.insert(url.clone(), list);
+                    }
+                    _ => data.remote_list = Some(list),
+                }
+                update
+            }
+            None => {
+                let list = cached_list(data, source).cloned().unwrap_or_default();
+                SourceUpdate {
+                    servers: list.servers.len(),
+                    version: list.version,
+                    schema: list.schema,
+                    skipped: 0,
+                    not_modified: true,
+                }
+            }
+        });
+        outcomes.push((source.clone(), outcome));
+    }
+    
+    // Forget mirrors that were removed from the config
+    data.source_lists.retain(|url, _| sources.contains(&ServerSource::Url(url.clone())));
+    data.source_validators.retain(|url, _| url == REMOTE_SERVER_LIST_URL || data.source_lists.contains_key(url));
+    if outcomes.iter().any(|(_, outcome)| outcome.is_ok()) {
+        data.cache_timestamp = Utc::now();
+    }
+    outcomes
+}
+
+fn cached_list<'a>(data: &'a LocalServerData, source: &ServerSource) -> Option<&'a ServerList> {
+    match source {
+        ServerSource::Default => data.remote_list.as_ref(),
+        ServerSource::Url(url) => data.source_lists.get(url),
+        ServerSource::Path(_) => None,
+    }
+}
+
+// Quarantined servers whose cool-down has passed
+pub fn quarantine_reprobe_due(data: &LocalServerData, config: &Config) -> Vec<ServerMetadata> {
+    let policy = HealthPolicy::from_config(config);
+    get_full_server_list(data, config)
+        .into_iter()
+        .filter(|s| data.health.get(&s.url).is_some_and(|h| h.reprobe_due(&policy)))
         .collect()
 }
+
+// Probes every server concurrently and returns (index, RTT in ms) for those that answered,
+// fastest first
+pub async fn rank_servers_by_latency(servers: &[ServerMetadata]) -> Vec<(usize, f64)> {
+    use futures_util::StreamExt;
+
+    let mut ranked: Vec<(usize, f64)> = futures_util::stream::iter(servers.iter().enumerate())
+        .map(|(i, server)| async move { crate::latency::quick_rtt(&server.url).await.map(|rtt| (i, rtt)) })
+        .buffer_unordered(AUTO_PROBE_CONCURRENCY)
+        .filter_map(|r| async move { r })
+        .collect()
+        .await;
+
+    ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+    ranked
+}
+
+// The server picked by an earlier --auto run, if it is younger than `ttl` and still a candidate
+pub fn cached_auto_selection(data: &LocalServerData, servers: &[ServerMetadata], ttl: std::time::Duration) -> Option<ServerMetadata> {
+    let cached = data.auto_selection.as_ref()?;
+    let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+    if Utc::now().signed_duration_since(cached.selected_at) >= ttl {
+        return None;
+    }
+    servers.iter().find(|s| s.url == cached.url).cloned()
+}
+
+// Probes `servers` and records the lowest-latency one as the --auto choice
+pub async fn select_closest_server(data: &mut LocalServerData, servers: &[ServerMetadata]) -> Option<ServerMetadata> {
+    let (index, latency_ms) = *rank_servers_by_latency(servers).await.first()?;
+    let server = servers[index].clone();
+    data.auto_selection = Some(AutoSelection {
+        url: server.url.clone(),
+        name: server.name.clone(),
+        latency_ms,
+        selected_at: Utc::now(),
+    });
+    Some(server)
+}
+
+// Records a test outcome in the persisted health data. The file is re-read first so
+// changes made while the test was running are not overwritten.
+pub fn record_health(url: &str, sample: HealthSample) -> std::io::Result<()> {
+    record_health_samples(&[(url.to_string(), sample)])
+}
+
+// Records several outcomes with a single read and write of the data file
+pub fn record_health_samples(samples: &[(String, HealthSample)]) -> std::io::Result<()> {
+    let mut data = load_local_server_data();
+    for (url, sample) in samples {
+        data.health
+            .entry(url.clone())
+            .or_insert_with(|| ServerHealth::new(url))
+            .record(sample);
+    }
+    save_local_server_data(&data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema_of(value: serde_json::Value) -> u32 {
+        ServerList::from_value(value).unwrap().0.schema
+    }
+
+    fn policy() -> HealthPolicy {
+        HealthPolicy {
+            quarantine_after_failures: 3,
+            demote_below_success_rate: 0.5,
+            cooldown: chrono::Duration::hours(6),
+        }
+    }
+
+    // `true` is a successful test, `false` a failed one
+    fn health(outcomes: &[bool]) -> ServerHealth {
+        let mut health = ServerHealth::new("https://example.com/100MB.bin");
+        for &success in outcomes {
+            let sample = if success {
+                HealthSample { success, speed_mbps: Some(100.0), latency_ms: Some(20.0) }
+            } else {
+                HealthSample::failure()
+            };
+            health.record(&sample);
+        }
+        health
+    }
+
+    #[test]
+    fn consecutive_failures_quarantine() {
+        assert_eq!(health(&[true, true, true, false, false]).standing(&policy()), Standing::Healthy);
+        assert_eq!(health(&[true, true, true, false, false, false]).standing(&policy()), Standing::Quarantined);
+        // Failures interrupted by a success do not add up
+        assert_eq!(health(&[true, true, true, false, false, true, false, false]).standing(&policy()), Standing::Healthy);
+    }
+
+    #[test]
+    fn a_success_lifts_the_quarantine() {
+        let mut health = health(&[true, true, true, true, false, false, false]);
+        assert_eq!(health.standing(&policy()), Standing::Quarantined);
+        health.record(&HealthSample { success: true, speed_mbps: None, latency_ms: None });
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.standing(&policy()), Standing::Healthy);
+    }
+
+    #[test]
+    fn low_success_rate_demotes_after_enough_checks() {
+        // Two checks are too few to judge
+        assert_eq!(health(&[false, true]).standing(&policy()), Standing::Healthy);
+        assert_eq!(health(&[false, true, false, true, false, true, false, true, true, false, false, true]).standing(&policy()), Standing::Healthy);
+        let demoted = health(&[false, false, true, false, false, true]);
+        assert!(demoted.success_rate < 0.5);
+        assert_eq!(demoted.standing(&policy()), Standing::Demoted);
+        // Recovering above the threshold restores it
+        let recovered = health(&[false, false, true, false, false, true, true, true, true]);
+        assert_eq!(recovered.standing(&policy()), Standing::Healthy);
+    }
+
+    #[test]
+    fn zero_threshold_never_quarantines() {
+        let policy = HealthPolicy { quarantine_after_failures: 0, ..policy() };
+        assert_eq!(health(&[false; 10]).standing(&policy), Standing::Demoted);
+    }
+
+    #[test]
+    fn quarantined_server_is_reprobed_after_the_cooldown() {
+        let mut health = health(&[false, false, false]);
+        assert!(!health.reprobe_due(&policy()));
+        health.last_checked = Some(Utc::now() - chrono::Duration::hours(7));
+        assert!(health.reprobe_due(&policy()));
+    }
+
+    #[test]
+    fn missing_schema_is_the_first_schema() {
+        assert_eq!(schema_of(serde_json::json!({ "servers": [] })), 1);
+    }
+
+    #[test]
+    fn schema_zero_is_kept() {
+        assert_eq!(schema_of(serde_json::json!({ "schema": 0, "servers": [] })), 0);
+    }
+
+    #[test]
+    fn newer_schema_is_kept_and_unreadable_entries_skipped() {
+        let value = serde_json::json!({
+            "schema": SERVER_LIST_SCHEMA + 1,
+            "servers": [
+                { "name": "Old", "url": "https://example.com/100MB.bin" },
+                { "name": { "en": "New" }, "url": "https://example.com/new.bin" },
+            ],
+        });
+        let (list, skipped) = ServerList::from_value(value).unwrap();
+        assert_eq!(list.schema, SERVER_LIST_SCHEMA + 1);
+        assert_eq!(list.servers.len(), 1);
+        assert_eq!(skipped, 1);
+    }
+
+    #[test]
+    fn schema_beyond_u32_is_newer_than_supported() {
+        let schema = schema_of(serde_json::json!({ "schema": u64::from(u32::MAX) + 1, "servers": [] }));
+        assert_eq!(schema, u32::MAX);
+        assert!(schema > SERVER_LIST_SCHEMA);
+    }
+}
@@ -0,0 +1,157 @@
+// Server list sources from the config's `server_sources`.
+// A source is the signed public list ("default"), another HTTP(S) URL such as a company mirror,
+// a local JSON/TOML file, or a directory of such fragments.
+
+use crate::servers::ServerList;
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_SOURCE: &str = "default";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ServerSource {
+    // The public list from the speedo repository, checked against the embedded key
+    Default,
+    Url(String),
+    // A file, or a directory whose *.json and *.toml files are read in name order
+    Path(PathBuf),
+}
+
+impl ServerSource {
+    pub fn parse(s: &str) -> Self {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case(DEFAULT_SOURCE) {
+            ServerSource::Default
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            ServerSource::Url(s.to_string())
+        } else if let (Some(rest), Some(home)) = (s.strip_prefix("~/"), dirs::home_dir()) {
+            ServerSource::Path(home.join(rest))
+        } else {
+            ServerSource::Path(PathBuf::from(s))
+        }
+    }
+}
+
+impl std::fmt::Display for ServerSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerSource::Default => write!(f, "{}", DEFAULT_SOURCE),
+            ServerSource::Url(url) => write!(f, "{}", url),
+            ServerSource::Path(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+// Sources in precedence order: when two sources list the same URL, the earlier one wins
+pub fn configured_sources(config: &crate::config::Config) -> Vec<ServerSource> {
+    config.server_sources.iter().map(|s| ServerSource::parse(s)).collect()
+}
+
+// Cache validators from the last successful fetch, sent back so an unchanged list costs a 304
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Validators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+pub enum Fetched {
+    NotModified,
+    Body(bytes::Bytes, Validators),
+}
+
+pub fn client() -> Result<reqwest::Client, reqwest::Error> {
+    reqwest::Client::builder()
+        .user_agent("speedo")
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+}
+
+// GET with If-None-Match / If-Modified-Since when the previous fetch left validators
+pub async fn conditional_get(client: &reqwest::Client, url: &str, validators: Option<&Validators>) -> Result<Fetched, Box<dyn std::error::Error>> {
+    let mut request = client.get(url);
+    if let Some(validators) = validators {
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    let response = request.send().await?;
+    if validators.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Fetched::NotModified);
+    }
+
+    let response = response.error_for_status()?;
+    let header = |name: reqwest::header::HeaderName| {
+        response.headers().get(name).and_then(|v| v.to_str().ok()).map(str::to_string)
+    };
+    let validators = Validators {
+        etag: header(ETAG),
+        last_modified: header(LAST_MODIFIED),
+    };
+    Ok(Fetched::Body(response.bytes().await?, validators))
+}
+
+// Fetches a server list from a URL source. Mirrors are trusted because the user configured
+// them, so unlike the default list they are not signature checked.
+// Returns None when the server reports the cached copy is still current.
+pub async fn fetch_url_source(url: &str, validators: Option<&Validators>) -> Result<Option<(ServerList, usize, Validators)>, Box<dyn std::error::Error>> {
+    match conditional_get(&client()?, url, validators).await? {
+        Fetched::NotModified => Ok(None),
+        Fetched::Body(body, validators) => {
+            let (list, skipped) = parse_fragment(&String::from_utf8_lossy(&body), url_is_toml(url))?;
+            Ok(Some((list, skipped, validators)))
+        }
+    }
+}
+
+// Reads a local file, or every fragment in a directory, into one list. Also returns how many
+// entries were skipped because they could not be read.
+pub fn load_path_source(path: &Path) -> Result<(ServerList, usize), Box<dyn std::error::Error>> {
+    if !path.is_dir() {
+        let contents = std::fs::read_to_string(path)?;
+        return parse_fragment(&contents, is_toml(&path.to_string_lossy()))
+            .map_err(|e| format!("{}: {}", path.display(), e).into());
+    }
+
+    let mut fragments: Vec<PathBuf> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && matches!(p.extension().and_then(|e| e.to_str()), Some("json" | "toml")))
+        .collect();
+    fragments.sort();
+
+    let mut merged = ServerList::default();
+    let mut skipped = 0;
+    for fragment in fragments {
+        let (list, fragment_skipped) = load_path_source(&fragment)?;
+        merged.schema = merged.schema.max(list.schema);
+        merged.servers.extend(list.servers);
+        skipped += fragment_skipped;
+    }
+    Ok((merged, skipped))
+}
+
+// A fragment is a full servers.json, or just its `servers` array ([[servers]] tables in TOML)
+pub fn parse_fragment(contents: &str, toml: bool) -> Result<(ServerList, usize), Box<dyn std::error::Error>> {
+    let value: serde_json::Value = if toml {
+        toml::from_str(contents)?
+    } else {
+        serde_json::from_str(contents)?
+    };
+    ServerList::from_value(value)
+}
+
+pub fn is_toml(name: &str) -> bool {
+    name.to_lowercase().ends_with(".toml")
+}
+
+// Only the URL's path counts, so `list.toml?token=...` is still TOML
+fn url_is_toml(url: &str) -> bool {
+    reqwest::Url::parse(url).is_ok_and(|url| is_toml(url.path()))
+}
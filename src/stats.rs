@@ -0,0 +1,310 @@
+// Statistics over the run history for `speedo history stats`.
+// Summarises speeds per server and per hour of day, and compares the last week with the week
+// before and with the whole period.
+
+use chrono::{DateTime, Duration, Local, Timelike, Utc};
+use serde::Serialize;
+use crate::history::{HistoryMode, HistoryRecord, RunStatus};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Download,
+    Upload,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Download => "download",
+            Direction::Upload => "upload",
+        }
+    }
+
+    fn of(mode: HistoryMode) -> &'static [Direction] {
+        match mode {
+            HistoryMode::Download => &[Direction::Download],
+            HistoryMode::Upload => &[Direction::Upload],
+            HistoryMode::Bidir => &[Direction::Download, Direction::Upload],
+        }
+    }
+
+    // A run's speed in this direction. Runs from a parallel batch only measured a share of
+    // the link, so they are left out.
+    pub fn speed(&self, record: &HistoryRecord) -> Option<f64> {
+        if record.parallel.is_some() {
+            return None;
+        }
+        match self {
+            Direction::Download => record.download_mbps,
+            Direction::Upload => record.upload_mbps,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Summary {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    // Population standard deviation
+    pub stddev: f64,
+}
+
+impl Summary {
+    pub fn from_values(values: &[f64]) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Some(Self {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            median: percentile(&sorted, 50.0),
+            p95: percentile(&sorted, 95.0),
+            stddev: variance.sqrt(),
+        })
+    }
+}
+
+// Linear interpolation between the closest ranks of a sorted, non-empty slice
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    Summary::from_values(values).map(|s| s.median)
+}
+
+// Change from `reference` to `value` in percent; negative means slower
+fn change_pct(value: f64, reference: f64) -> Option<f64> {
+    (reference > 0.0).then(|| (value - reference) / reference * 100.0)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Trend {
+    // Median of the last seven days of the period, and of the seven days before
+    pub this_week_median: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_week_median: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub week_over_week_pct: Option<f64>,
+    // This week against the median of the whole period
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vs_period_median_pct: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStats {
+    pub server: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub direction: Direction,
+    pub runs: usize,
+    pub failed: usize,
+    // None when no run in the period produced a speed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed_mbps: Option<Summary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trend: Option<Trend>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HourStats {
+    // Local hour of day, 0-23
+    pub hour: u32,
+    pub direction: Direction,
+    pub runs: usize,
+    pub failed: usize,
+    pub speed_mbps: Summary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryStats {
+    pub since: DateTime<Utc>,
+    pub until: DateTime<Utc>,
+    pub runs: usize,
+    pub failed: usize,
+    pub servers: Vec<ServerStats>,
+    pub hours: Vec<HourStats>,
+}
+
+impl HistoryStats {
+    // `records` are the runs in [since, until)
+    pub fn compute(records: &[HistoryRecord], since: DateTime<Utc>, until: DateTime<Utc>) -> Self {
+        let this_week = until - Duration::days(7);
+        let last_week = this_week - Duration::days(7);
+
+        let mut servers = Vec::new();
+        for direction in [Direction::Download, Direction::Upload] {
+            let runs: Vec<&HistoryRecord> = records.iter().filter(|r| Direction::of(r.mode).contains(&direction)).collect();
+
+            // Grouped by URL, since names are not unique; first seen order
+            let mut urls: Vec<&str> = Vec::new();
+            for record in &runs {
+                if !urls.contains(&record.url.as_str()) {
+                    urls.push(&record.url);
+                }
+            }
+
+            let mut group_stats: Vec<ServerStats> = urls.iter()
+                .map(|url| {
+                    let group: Vec<&&HistoryRecord> = runs.iter().filter(|r| r.url == *url).collect();
+                    let latest = group[group.len() - 1];
+                    let speeds = |from: DateTime<Utc>, to: DateTime<Utc>| -> Vec<f64> {
+                        group.iter()
+                            .filter(|r| r.timestamp >= from && r.timestamp < to)
+                            .filter_map(|r| direction.speed(r))
+                            .collect()
+                    };
+                    let speed_mbps = Summary::from_values(&speeds(since, until));
+                    let trend = median(&speeds(this_week, until)).map(|this_week_median| {
+                        let last_week_median = median(&speeds(last_week, this_week));
+                        Trend {
+                            this_week_median,
+                            last_week_median,
+                            week_over_week_pct: last_week_median.and_then(|m| change_pct(this_week_median, m)),
+                            vs_period_median_pct: speed_mbps.and_then(|s| change_pct(this_week_median, s.median)),
+                        }
+                    });
+
+                    ServerStats {
+                        server: latest.server.clone(),
+                        url: latest.url.clone(),
+                        provider: latest.provider.clone(),
+                        direction,
+                        runs: group.len(),
+                        failed: group.iter().filter(|r| r.status == RunStatus::Failed).count(),
+                        speed_mbps,
+                        trend,
+                    }
+                })
+                .collect();
+            // Fastest first, servers without a speed last
+            group_stats.sort_by(|a, b| {
+                let median = |s: &ServerStats| s.speed_mbps.map(|s| s.median).unwrap_or(-1.0);
+                median(b).partial_cmp(&median(a)).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            servers.extend(group_stats);
+        }
+
+        let mut hours = Vec::new();
+        for direction in [Direction::Download, Direction::Upload] {
+            for hour in 0..24 {
+                let runs: Vec<&HistoryRecord> = records.iter()
+                    .filter(|r| Direction::of(r.mode).contains(&direction))
+                    .filter(|r| r.timestamp.with_timezone(&Local).hour() == hour)
+                    .collect();
+                let speeds: Vec<f64> = runs.iter().filter_map(|r| direction.speed(r)).collect();
+                if let Some(speed_mbps) = Summary::from_values(&speeds) {
+                    hours.push(HourStats {
+                        hour,
+                        direction,
+                        runs: runs.len(),
+                        failed: runs.iter().filter(|r| r.status == RunStatus::Failed).count(),
+                        speed_mbps,
+                    });
+                }
+            }
+        }
+
+        HistoryStats {
+            since,
+            until,
+            runs: records.len(),
+            failed: records.iter().filter(|r| r.status == RunStatus::Failed).count(),
+            servers,
+            hours,
+        }
+    }
+
+    // Length of the period in whole days, for "your 30-day median"
+    pub fn period_days(&self) -> i64 {
+        ((self.until - self.since).num_hours() as f64 / 24.0).round() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SpeedUnit;
+    use chrono::TimeZone;
+
+    fn run(days_before: i64, mbps: f64, until: DateTime<Utc>) -> HistoryRecord {
+        let mut record = HistoryRecord::new("Test", "http://example.com/file", None, HistoryMode::Download, SpeedUnit::BitsMetric);
+        record.timestamp = until - Duration::days(days_before);
+        record.download_mbps = Some(mbps);
+        record
+    }
+
+    #[test]
+    fn one_sample_is_every_percentile() {
+        let summary = Summary::from_values(&[42.0]).unwrap();
+        assert_eq!(summary.count, 1);
+        assert_eq!(summary.median, 42.0);
+        assert_eq!(summary.p95, 42.0);
+        assert_eq!(summary.stddev, 0.0);
+    }
+
+    #[test]
+    fn even_count_interpolates_between_the_middle_values() {
+        let summary = Summary::from_values(&[40.0, 10.0, 30.0, 20.0]).unwrap();
+        assert_eq!(summary.min, 10.0);
+        assert_eq!(summary.max, 40.0);
+        assert_eq!(summary.median, 25.0);
+        // Rank 0.95 * 3 = 2.85, between 30 and 40
+        assert!((summary.p95 - 38.5).abs() < 1e-9);
+        assert!((summary.stddev - 125.0_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn no_values_no_summary() {
+        assert!(Summary::from_values(&[]).is_none());
+    }
+
+    #[test]
+    fn trend_without_a_previous_week() {
+        let until = Utc.with_ymd_and_hms(2025, 11, 30, 0, 0, 0).unwrap();
+        let since = until - Duration::days(30);
+        let records = [run(1, 100.0, until), run(2, 200.0, until)];
+
+        let stats = HistoryStats::compute(&records, since, until);
+        let trend = stats.servers[0].trend.as_ref().unwrap();
+        assert_eq!(trend.this_week_median, 150.0);
+        assert_eq!(trend.last_week_median, None);
+        assert_eq!(trend.week_over_week_pct, None);
+        assert_eq!(trend.vs_period_median_pct, Some(0.0));
+    }
+
+    #[test]
+    fn trend_week_over_week() {
+        let until = Utc.with_ymd_and_hms(2025, 11, 30, 0, 0, 0).unwrap();
+        let since = until - Duration::days(30);
+        let records = [run(10, 100.0, until), run(1, 150.0, until)];
+
+        let stats = HistoryStats::compute(&records, since, until);
+        let trend = stats.servers[0].trend.as_ref().unwrap();
+        assert_eq!(trend.last_week_median, Some(100.0));
+        assert_eq!(trend.week_over_week_pct, Some(50.0));
+    }
+
+    #[test]
+    fn parallel_runs_have_no_speed() {
+        let until = Utc.with_ymd_and_hms(2025, 11, 30, 0, 0, 0).unwrap();
+        let record = run(1, 100.0, until).with_parallel(4);
+        assert_eq!(Direction::Download.speed(&record), None);
+    }
+}
@@ -0,0 +1,195 @@
+// Connection phase timing for HTTP requests.
+// Hooks the reqwest resolver and connector to measure DNS and connection setup on the same
+// connection the download uses.
+
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::Client;
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+use tower_layer::Layer;
+use tower_service::Service;
+
+// Durations in seconds for each phase of a single request.
+// `server_wait` runs from the request being sent on the ready connection until the
+// response headers arrive; `transfer` runs from there until the body is complete.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct PhaseTimings {
+    pub dns: f64,
+    // TCP connect, plus the TLS handshake for HTTPS. reqwest performs both inside one
+    // connector call, so they cannot be timed apart on the connection the request uses.
+    pub connect: f64,
+    // Whether `connect` includes a TLS handshake
+    pub tls: bool,
+    pub server_wait: f64,
+    pub transfer: f64,
+}
+
+impl PhaseTimings {
+    // Name of the connect phase for display
+    pub fn connect_label(&self) -> &'static str {
+        if self.tls { "TCP+TLS" } else { "TCP" }
+    }
+}
+
+#[derive(Debug, Default)]
+struct PhaseMarks {
+    dns_start: Option<Instant>,
+    dns_end: Option<Instant>,
+    connect_start: Option<Instant>,
+    connect_end: Option<Instant>,
+}
+
+// Shared between the resolver, the connector layer and the download loop.
+#[derive(Debug, Clone)]
+pub struct PhaseRecorder {
+    marks: Arc<Mutex<PhaseMarks>>,
+    tls: bool,
+}
+
+impl PhaseRecorder {
+    fn new(url: &reqwest::Url) -> Self {
+        Self {
+            marks: Arc::new(Mutex::new(PhaseMarks::default())),
+            tls: url.scheme() == "https",
+        }
+    }
+
+    // Seconds from `start` until the connection was ready for the request
+    pub fn connect_time(&self, start: Instant) -> Option<f64> {
+        let marks = self.marks.lock().unwrap();
+        marks.connect_end.map(|end| end.saturating_duration_since(start).as_secs_f64())
+    }
+
+    pub fn timings(&self, start: Instant, headers_at: Instant, end: Instant) -> PhaseTimings {
+        let marks = self.marks.lock().unwrap();
+        let (dns, connect) = self.connection_phases(&marks);
+        let ready = marks.connect_end.unwrap_or(start);
+
+        PhaseTimings {
+            dns,
+            connect,
+            tls: self.tls,
+            server_wait: headers_at.saturating_duration_since(ready).as_secs_f64(),
+            transfer: end.saturating_duration_since(headers_at).as_secs_f64(),
+        }
+    }
+
+    // For uploads the transfer is the request body. Handing the last chunk to the connection
+    // does not mean it has left the send buffer, so the transfer runs until the server
+    // answers, which it only does once it has read the whole body. Its processing time
+    // cannot be told apart, so there is no separate server wait.
+    pub fn upload_timings(&self, start: Instant, headers_at: Instant) -> PhaseTimings {
+        let marks = self.marks.lock().unwrap();
+        let (dns, connect) = self.connection_phases(&marks);
+        let ready = marks.connect_end.unwrap_or(start);
+
+        PhaseTimings {
+            dns,
+            connect,
+            tls: self.tls,
+            server_wait: 0.0,
+            transfer: headers_at.saturating_duration_since(ready).as_secs_f64(),
+        }
+    }
+
+    fn connection_phases(&self, marks: &PhaseMarks) -> (f64, f64) {
+        let dns = match (marks.dns_start, marks.dns_end) {
+            (Some(s), Some(e)) => e.saturating_duration_since(s).as_secs_f64(),
+            _ => 0.0,
+        };
+
+        // The connector resolves DNS itself, so its duration includes the lookup
+        let connect = match (marks.connect_start, marks.connect_end) {
+            (Some(s), Some(e)) => (e.saturating_duration_since(s).as_secs_f64() - dns).max(0.0),
+            _ => 0.0,
+        };
+
+        (dns, connect)
+    }
+}
+
+// Builds a client whose resolver and connector report into a fresh recorder.
+// Each client should only be used for one request so the marks describe one connection.
+pub fn timed_client(url: &str, user_agent: &str) -> Result<(Client, PhaseRecorder), Box<dyn std::error::Error>> {
+    let parsed = reqwest::Url::parse(url)?;
+    let recorder = PhaseRecorder::new(&parsed);
+
+    let client = Client::builder()
+        .user_agent(user_agent)
+        .dns_resolver(Arc::new(TimingResolver { recorder: recorder.clone() }))
+        .connector_layer(TimingLayer { recorder: recorder.clone() })
+        .build()?;
+
+    Ok((client, recorder))
+}
+
+struct TimingResolver {
+    recorder: PhaseRecorder,
+}
+
+impl Resolve for TimingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let recorder = self.recorder.clone();
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            recorder.marks.lock().unwrap().dns_start = Some(Instant::now());
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            recorder.marks.lock().unwrap().dns_end = Some(Instant::now());
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[derive(Clone)]
+struct TimingLayer {
+    recorder: PhaseRecorder,
+}
+
+impl<S> Layer<S> for TimingLayer {
+    type Service = TimingService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TimingService {
+            inner,
+            recorder: self.recorder.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct TimingService<S> {
+    inner: S,
+    recorder: PhaseRecorder,
+}
+
+impl<S, Req> Service<Req> for TimingService<S>
+where
+    S: Service<Req>,
+    S::Future: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Req) -> Self::Future {
+        let recorder = self.recorder.clone();
+        recorder.marks.lock().unwrap().connect_start = Some(Instant::now());
+        let future = self.inner.call(req);
+        Box::pin(async move {
+            let result = future.await;
+            recorder.marks.lock().unwrap().connect_end = Some(Instant::now());
+            result
+        })
+    }
+}
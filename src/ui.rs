@@ -4,11 +4,31 @@
 use colored::*;
 use inquire::{Select, Text};
 use bytesize::ByteSize;
-use crate::servers::{ServerMetadata, LocalServerData};
+use crate::batch::{BatchEntry, BatchReport};
+use crate::bidir::BidirResult;
+use crate::config::{Config, SpeedUnit};
+use crate::downloader::{DownloadResult, StreamResult};
+use crate::geo::Location;
+use crate::history::{HistoryMode, HistoryRecord, RunStatus};
+use crate::latency::BufferbloatGrade;
+use crate::servers::{ServerMetadata, LocalServerData, HealthPolicy, Standing};
+use crate::stats::{Direction, HistoryStats, Summary};
+use crate::uploader::UploadResult;
+use crate::verify::{Verdict, VerifyReport};
 use std::collections::{HashMap, HashSet};
 use std::sync::OnceLock;
 
 static CACHED_TITLE: OnceLock<String> = OnceLock::new();
+static HOME_LOCATION: OnceLock<Option<Location>> = OnceLock::new();
+
+// Home for the "Nearest servers" menu entry, set once at startup
+pub fn set_home_location(home: Option<Location>) {
+    HOME_LOCATION.set(home).ok();
+}
+
+fn home_location() -> Option<&'static Location> {
+    HOME_LOCATION.get().and_then(|h| h.as_ref())
+}
 
 fn get_title() -> &'static str {
     CACHED_TITLE.get_or_init(|| {
@@ -24,7 +44,7 @@ pub enum ServerSelection {
 }
 
 pub enum ServerOption {
-    Server(ServerMetadata, Option<String>, Color), // server, health info, and color
+    Server(Box<ServerMetadata>, Option<String>, Color), // server, health info, and color
     Back,
 }
 
@@ -119,8 +139,9 @@ impl std::fmt::Display for ProviderOption {
 }
 
 pub enum MenuOption {
-    GlobalServer(ServerMetadata),
+    GlobalServer(Box<ServerMetadata>, bool), // carries whether it is quarantined
     BrowseAll(usize), // carries server count
+    BrowseNearest(String), // carries the home label
     BrowseByRegion,
     BrowseByProvider,
     Search,
@@ -130,13 +151,18 @@ pub enum MenuOption {
 impl std::fmt::Display for MenuOption {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            MenuOption::GlobalServer(server) => {
+            MenuOption::GlobalServer(server, quarantined) => {
                 write!(f, "🌐  {} - {}", 
                     server.name,
                     server.location.as_ref().unwrap_or(&"Global CDN".to_string())
-                )
+                )?;
+                if *quarantined {
+                    write!(f, " {}", "⛔ quarantined".red())?;
+                }
+                Ok(())
             }
             MenuOption::BrowseAll(count) => write!(f, "🌍  Browse all servers ({} servers)", count),
+            MenuOption::BrowseNearest(home) => write!(f, "🧭  Nearest servers (from {})", home),
             MenuOption::BrowseByRegion => write!(f, "🗺️  Browse by region"),
             MenuOption::BrowseByProvider => write!(f, "🏢  Browse by provider"),
             MenuOption::Search => write!(f, "🔍  Search servers"),
@@ -146,8 +172,9 @@ impl std::fmt::Display for MenuOption {
 }
 
 pub enum MenuSelection {
-    Server(ServerMetadata),
+    Server(Box<ServerMetadata>),
     BrowseAll,
+    BrowseNearest,
     BrowseByRegion,
     BrowseByProvider,
     Search,
@@ -173,7 +200,7 @@ pub fn wait_for_continue() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn get_main_menu_selection(servers: &[ServerMetadata]) -> Result<MenuSelection, Box<dyn std::error::Error>> {
+fn get_main_menu_selection(servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<MenuSelection, Box<dyn std::error::Error>> {
     print!("\x1B[2J\x1B[1;1H");
     print!("{}", get_title());
     
@@ -187,11 +214,15 @@ fn get_main_menu_selection(servers: &[ServerMetadata]) -> Result<MenuSelection,
     
     // Add global servers first
     for server in global_servers {
-        options.push(MenuOption::GlobalServer(server));
+        let quarantined = policy.standing(health_data, &server.url) == Standing::Quarantined;
+        options.push(MenuOption::GlobalServer(Box::new(server), quarantined));
     }
     
     // Add browsing options
     options.push(MenuOption::BrowseAll(servers.len()));
+    if let Some(home) = home_location() {
+        options.push(MenuOption::BrowseNearest(home.label.clone()));
+    }
     options.push(MenuOption::BrowseByRegion);
     options.push(MenuOption::BrowseByProvider);
     options.push(MenuOption::Search);
@@ -202,8 +233,9 @@ fn get_main_menu_selection(servers: &[ServerMetadata]) -> Result<MenuSelection,
     
     // Convert MenuOption to MenuSelection
     match selection {
-        MenuOption::GlobalServer(server) => Ok(MenuSelection::Server(server)),
+        MenuOption::GlobalServer(server, _) => Ok(MenuSelection::Server(server)),
         MenuOption::BrowseAll(_) => Ok(MenuSelection::BrowseAll),
+        MenuOption::BrowseNearest(_) => Ok(MenuSelection::BrowseNearest),
         MenuOption::BrowseByRegion => Ok(MenuSelection::BrowseByRegion),
         MenuOption::BrowseByProvider => Ok(MenuSelection::BrowseByProvider),
         MenuOption::Search => Ok(MenuSelection::Search),
@@ -216,7 +248,7 @@ fn group_servers_by_region(servers: &[ServerMetadata]) -> HashMap<String, Vec<Se
     
     for server in servers {
         let region = server.region.clone().unwrap_or_else(|| "Other".to_string());
-        map.entry(region).or_insert_with(Vec::new).push(server.clone());
+        map.entry(region).or_default().push(server.clone());
     }
     
     map
@@ -227,13 +259,13 @@ fn group_servers_by_provider(servers: &[ServerMetadata]) -> HashMap<String, Vec<
     
     for server in servers {
         let provider = server.provider.clone().unwrap_or_else(|| "Other".to_string());
-        map.entry(provider).or_insert_with(Vec::new).push(server.clone());
+        map.entry(provider).or_default().push(server.clone());
     }
     
     map
 }
 
-fn select_from_list(servers: &[ServerMetadata], health_data: &LocalServerData) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+fn select_from_list(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
     // Build color map once for all servers
     let color_map = build_provider_color_map(servers);
     
@@ -248,9 +280,10 @@ fn select_from_list(servers: &[ServerMetadata], health_data: &LocalServerData) -
         } else {
             None
         };
+        let speed_info = with_standing_marker(speed_info.unwrap_or_default(), s, health_data, policy);
         
         let color = get_provider_color(&s.provider, &color_map);
-        ServerOption::Server(s.clone(), speed_info, color)
+        ServerOption::Server(Box::new(s.clone()), speed_info, color)
     }).collect();
     
     options.push(ServerOption::Back);
@@ -260,12 +293,12 @@ fn select_from_list(servers: &[ServerMetadata], health_data: &LocalServerData) -
         .prompt()?;
     
     match selection {
-        ServerOption::Server(server, _, _) => Ok(ServerSelection::Server(server)),
-        ServerOption::Back => show_menu(),
+        ServerOption::Server(server, _, _) => Ok(ServerSelection::Server(*server)),
+        ServerOption::Back => show_menu(config),
     }
 }
 
-fn browse_by_region(servers: &[ServerMetadata], health_data: &LocalServerData) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+fn browse_by_region(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
     let grouped = group_servers_by_region(servers);
     
     let mut options: Vec<RegionOption> = grouped.iter()
@@ -285,13 +318,13 @@ fn browse_by_region(servers: &[ServerMetadata], health_data: &LocalServerData) -
     match selection {
         RegionOption::Region(region, _) => {
             let region_servers = grouped.get(&region).unwrap();
-            select_from_list(region_servers, health_data)
+            select_from_list(config, region_servers, health_data, policy)
         }
-        RegionOption::Back => show_menu(),
+        RegionOption::Back => show_menu(config),
     }
 }
 
-fn browse_by_provider(servers: &[ServerMetadata], health_data: &LocalServerData) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+fn browse_by_provider(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
     let grouped = group_servers_by_provider(servers);
     
     let mut options: Vec<ProviderOption> = grouped.iter()
@@ -326,17 +359,70 @@ fn browse_by_provider(servers: &[ServerMetadata], health_data: &LocalServerData)
     match selection {
         ProviderOption::Provider(provider, _) => {
             let provider_servers = grouped.get(&provider).unwrap();
-            select_from_list(provider_servers, health_data)
+            select_from_list(config, provider_servers, health_data, policy)
         }
-        ProviderOption::Back => show_menu(),
+        ProviderOption::Back => show_menu(config),
     }
 }
 
-fn browse_all(servers: &[ServerMetadata], health_data: &LocalServerData) -> Result<ServerSelection, Box<dyn std::error::Error>> {
-    select_from_list(servers, health_data)
+// Flags quarantined and demoted servers so they are not offered as if they were fine
+fn with_standing_marker(info: String, server: &ServerMetadata, health_data: &LocalServerData, policy: &HealthPolicy) -> Option<String> {
+    let info = match health_data.health.get(&server.url) {
+        Some(h) => match h.standing(policy) {
+            Standing::Quarantined => format!(
+                "{} {}",
+                info,
+                format!("⛔ quarantined ({} failures in a row)", h.consecutive_failures).red()
+            ),
+            Standing::Demoted => format!(
+                "{} {}",
+                info,
+                format!("↓ {:.0}% success", h.success_rate * 100.0).yellow()
+            ),
+            Standing::Healthy => info,
+        },
+        None => info,
+    };
+    Some(info).filter(|i| !i.is_empty())
+}
+
+fn browse_all(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+    select_from_list(config, servers, health_data, policy)
 }
 
-fn search_servers(servers: &[ServerMetadata], health_data: &LocalServerData) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+fn browse_nearest(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
+    let Some(home) = home_location() else {
+        return show_menu(config);
+    };
+    
+    let color_map = build_provider_color_map(servers);
+    
+    let mut options: Vec<ServerOption> = crate::geo::rank_by_distance(servers, home)
+        .into_iter()
+        .map(|(s, km)| {
+            let speed_info = health_data.health.get(&s.url)
+                .filter(|h| h.avg_speed_mbps > 0.0)
+                .map(|h| format!(", {:.1} MB/s avg", h.avg_speed_mbps / 8.0))
+                .unwrap_or_default();
+            let color = get_provider_color(&s.provider, &color_map);
+            let info = with_standing_marker(format!(" ({:.0} km{})", km, speed_info), &s, health_data, policy);
+            ServerOption::Server(Box::new(s), info, color)
+        })
+        .collect();
+    
+    options.push(ServerOption::Back);
+    
+    let selection = Select::new(&format!("Servers nearest to {}:", home.label), options)
+        .with_page_size(20)
+        .prompt()?;
+    
+    match selection {
+        ServerOption::Server(server, _, _) => Ok(ServerSelection::Server(*server)),
+        ServerOption::Back => show_menu(config),
+    }
+}
+
+fn search_servers(config: &Config, servers: &[ServerMetadata], health_data: &LocalServerData, policy: &HealthPolicy) -> Result<ServerSelection, Box<dyn std::error::Error>> {
     let search_term = Text::new("Search servers:")
         .with_placeholder("Enter location, provider, or server name...")
         .prompt()?;
@@ -355,42 +441,43 @@ fn search_servers(servers: &[ServerMetadata], health_data: &LocalServerData) ->
     if filtered.is_empty() {
         println!("{}", format!("No servers found matching '{}'", search_term).yellow());
         wait_for_continue()?;
-        return show_menu();
+        return show_menu(config);
     }
     
     println!("{}", format!("Found {} servers matching '{}'", filtered.len(), search_term).green());
-    select_from_list(&filtered, health_data)
+    select_from_list(config, &filtered, health_data, policy)
 }
 
-pub fn show_menu() -> Result<ServerSelection, Box<dyn std::error::Error>> {
+pub fn show_menu(config: &Config) -> Result<ServerSelection, Box<dyn std::error::Error>> {
     // Load server data
     let server_data = crate::servers::load_local_server_data();
-    let servers = crate::servers::get_merged_server_list(&server_data);
+    let policy = HealthPolicy::from_config(config);
+    // Quarantined servers stay in the menu, marked, so the user can still choose them
+    let servers = crate::servers::get_full_server_list(&server_data, config);
     
     // Get main menu selection
-    let selection = get_main_menu_selection(&servers)?;
+    let selection = get_main_menu_selection(&servers, &server_data, &policy)?;
     
     match selection {
-        MenuSelection::Server(server) => Ok(ServerSelection::Server(server)),
-        MenuSelection::BrowseAll => browse_all(&servers, &server_data),
-        MenuSelection::BrowseByRegion => browse_by_region(&servers, &server_data),
-        MenuSelection::BrowseByProvider => browse_by_provider(&servers, &server_data),
-        MenuSelection::Search => search_servers(&servers, &server_data),
+        MenuSelection::Server(server) => Ok(ServerSelection::Server(*server)),
+        MenuSelection::BrowseAll => browse_all(config, &servers, &server_data, &policy),
+        MenuSelection::BrowseNearest => browse_nearest(config, &servers, &server_data, &policy),
+        MenuSelection::BrowseByRegion => browse_by_region(config, &servers, &server_data, &policy),
+        MenuSelection::BrowseByProvider => browse_by_provider(config, &servers, &server_data, &policy),
+        MenuSelection::Search => search_servers(config, &servers, &server_data, &policy),
         MenuSelection::Quit => Ok(ServerSelection::Quit),
     }
 }
 
-pub fn print_results(
-    status_code: u16,
-    connect_time: f64,
-    ttfb: f64,
-    total_time: f64,
-    bytes_downloaded: u64,
-    save_path: Option<String>,
-) {
+pub fn print_results(result: &DownloadResult, save_path: Option<String>) {
+    let status_code = result.status_code;
+    let total_time = result.total_time;
+    let bytes_downloaded = result.bytes_downloaded;
+    let timings = result.timings;
+    
     let size_mb = bytes_downloaded as f64 / 1_048_576.0;
-    let mbs = (bytes_downloaded as f64 / total_time) / 1_048_576.0;
-    let mbps = (bytes_downloaded as f64 * 8.0 / total_time) / 1_000_000.0;
+    let mbs = result.bytes_per_second() / 1_048_576.0;
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
     
     let size_str = ByteSize::b(bytes_downloaded).to_string();
     
@@ -403,7 +490,8 @@ pub fn print_results(
     println!("{} {} in {}", "Downloaded".green(), size_str, time_str);
 
     println!();
-    if status_code == 200 {
+    // 206 Partial Content is what a resumed download legitimately returns
+    if status_code == 200 || status_code == 206 {
         println!("Status:  {}", format!("{} (OK)", status_code).green());
     } else {
         println!(
@@ -412,13 +500,51 @@ pub fn print_results(
         );
     }
 
-    println!("Connect: {:.3}s", connect_time);
-    println!("TTFB:    {:.3}s", ttfb);
+    println!("Connect: {:.3}s", result.connect_time);
+    println!("TTFB:    {:.3}s", result.ttfb);
     println!("Total:   {:.3}s", total_time);
+    println!(
+        "{}",
+        format!(
+            "  DNS {:.3}s | {} {:.3}s | Wait {:.3}s | Transfer {:.3}s",
+            timings.dns,
+            timings.connect_label(),
+            timings.connect,
+            timings.server_wait,
+            timings.transfer,
+        ).bright_black()
+    );
+    if let Some(warmup) = result.warmup {
+        println!(
+            "{}",
+            format!("  Warm-up: first {:.2}s ({}) excluded from speed", warmup.time, ByteSize::b(warmup.bytes)).bright_black()
+        );
+    }
+    if let Some(latency) = result.latency {
+        if let Some(idle) = latency.idle {
+            println!(
+                "Idle:    {:.1} ms  (min {:.1} / max {:.1}, jitter {:.1} ms)",
+                idle.avg, idle.min, idle.max, idle.jitter
+            );
+        }
+        if let Some(loaded) = latency.loaded {
+            println!(
+                "Loaded:  {:.1} ms  (min {:.1} / max {:.1}, jitter {:.1} ms)",
+                loaded.avg, loaded.min, loaded.max, loaded.jitter
+            );
+        }
+        if let Some(bufferbloat) = latency.bufferbloat() {
+            println!(
+                "Bloat:   {} (+{:.1} ms under load)",
+                grade_colored(bufferbloat.grade),
+                bufferbloat.latency_increase
+            );
+        }
+    }
     println!("----------------");
     println!("Size:    {:.2} MB", size_mb);
 
-    if size_mb < 10.0 {
+    if size_mb < 10.0 && !result.time_limited {
         println!(
             "{}",
             "WARNING: File is very small (<10MB). Speed result may be inaccurate.".magenta()
@@ -426,7 +552,7 @@ pub fn print_results(
     }
     println!("----------------");
 
-    if status_code == 200 {
+    if status_code == 200 || status_code == 206 {
         println!(
             "Speed:   {}",
             format!("{:.2} MB/s  ({:.2} Mbps)", mbs, mbps).green()
@@ -444,13 +570,13 @@ pub fn print_results(
     }
 }
 
-pub fn print_speed_only(
-    status_code: u16,
-    total_time: f64,
-    bytes_downloaded: u64,
-) {
-    let mbs = (bytes_downloaded as f64 / total_time) / 1_048_576.0;
-    let mbps = (bytes_downloaded as f64 * 8.0 / total_time) / 1_000_000.0;
+pub fn print_speed_only(result: &DownloadResult) {
+    let status_code = result.status_code;
+    let total_time = result.total_time;
+    let bytes_downloaded = result.bytes_downloaded;
+    
+    let mbs = result.bytes_per_second() / 1_048_576.0;
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
     
     let size_str = ByteSize::b(bytes_downloaded).to_string();
     
@@ -462,9 +588,615 @@ pub fn print_speed_only(
     
     print!("{} {} in {} - ", "Downloaded".green(), size_str, time_str);
 
-    if status_code == 200 {
+    if status_code == 200 || status_code == 206 {
         println!("{:.2} MB/s  ({:.2} Mbps)", mbs, mbps);
     } else {
         println!("{:.2} MB/s  ({:.2} Mbps) - (Error: status {})", mbs, mbps, status_code);
     }
 }
+
+pub fn print_upload_results(result: &UploadResult, detailed: bool) {
+    let status_code = result.status_code;
+    let total_time = result.total_time;
+    let timings = result.timings;
+    let ok = (200..300).contains(&status_code);
+
+    let mbs = result.bytes_per_second() / 1_048_576.0;
+    let mbps = (result.bytes_per_second() * 8.0) / 1_000_000.0;
+
+    let size_str = ByteSize::b(result.bytes_uploaded).to_string();
+
+    let time_str = if total_time >= 60.0 {
+        format!("{:.0}m {:.1}s", total_time / 60.0, total_time % 60.0)
+    } else {
+        format!("{:.2}s", total_time)
+    };
+
+    if !detailed {
+        print!("{} {} in {} - ", "Uploaded".green(), size_str, time_str);
+        if ok {
+            println!("{:.2} MB/s  ({:.2} Mbps)", mbs, mbps);
+        } else {
+            println!("{:.2} MB/s  ({:.2} Mbps) - (Error: status {})", mbs, mbps, status_code);
+        }
+        return;
+    }
+
+    println!("{} {} in {}", "Uploaded".green(), size_str, time_str);
+
+    println!();
+    if ok {
+        println!("Status:  {}", format!("{} (OK)", status_code).green());
+    } else {
+        println!(
+            "Status:  {}",
+            format!("{} (Error/Redirect)", status_code).red()
+        );
+    }
+
+    println!("Connect: {:.3}s", result.connect_time);
+    println!("Reply:   {:.3}s", result.ttfb);
+    println!("Total:   {:.3}s", total_time);
+    println!(
+        "{}",
+        format!(
+            "  DNS {:.3}s | {} {:.3}s | Send {:.3}s",
+            timings.dns,
+            timings.connect_label(),
+            timings.connect,
+            timings.transfer,
+        ).bright_black()
+    );
+    println!("----------------");
+
+    if ok {
+        println!(
+            "Upload:  {}",
+            format!("{:.2} MB/s  ({:.2} Mbps)", mbs, mbps).green()
+        );
+    } else {
+        println!(
+            "Upload:  {}",
+            format!("{:.2} MB/s  ({:.2} Mbps) - (Invalid due to Error)", mbs, mbps)
+                .bright_black()
+        );
+    }
+}
+
+pub fn print_bidir_results(result: &BidirResult, detailed: bool) {
+    if detailed {
+        print_results(&result.download, None);
+        print_stream_breakdown(&result.download.streams);
+        println!();
+        print_upload_results(&result.upload, true);
+        println!("----------------");
+    } else {
+        print_speed_only(&result.download);
+        print_latency(&result.download);
+        print_stream_breakdown(&result.download.streams);
+        print_upload_results(&result.upload, false);
+    }
+
+    let asymmetry = result.asymmetry();
+    let mode = result.mode.as_str();
+    if asymmetry.asymmetric {
+        println!(
+            "{}",
+            format!("Asymmetric link: {} is {:.1}x faster ({})", asymmetry.faster, asymmetry.ratio, mode).yellow()
+        );
+    } else {
+        println!(
+            "{}",
+            format!("Symmetric link: {:.1}x difference ({})", asymmetry.ratio, mode).bright_black()
+        );
+    }
+}
+
+pub fn print_latency(result: &DownloadResult) {
+    let Some(latency) = result.latency else {
+        return;
+    };
+
+    let describe = |stats: Option<crate::latency::LatencyStats>| match stats {
+        Some(s) => format!("{:.1} ms ±{:.1}", s.avg, s.jitter),
+        None => "n/a".to_string(),
+    };
+
+    print!(
+        "{}",
+        format!("Latency: idle {} | loaded {}", describe(latency.idle), describe(latency.loaded)).bright_black()
+    );
+    match latency.bufferbloat() {
+        Some(bufferbloat) => println!(" {} {}", "| bufferbloat".bright_black(), grade_colored(bufferbloat.grade)),
+        None => println!(),
+    }
+}
+
+fn grade_colored(grade: BufferbloatGrade) -> ColoredString {
+    match grade {
+        BufferbloatGrade::A => grade.as_str().green().bold(),
+        BufferbloatGrade::B => grade.as_str().green(),
+        BufferbloatGrade::C => grade.as_str().yellow(),
+        BufferbloatGrade::D => grade.as_str().red(),
+        BufferbloatGrade::F => grade.as_str().red().bold(),
+    }
+}
+
+pub fn print_stream_breakdown(streams: &[StreamResult]) {
+    if streams.is_empty() {
+        return;
+    }
+    
+    println!();
+    println!("{}", format!("Connections: {}", streams.len()).bright_black());
+    for stream in streams {
+        let mbps = (stream.bytes_downloaded as f64 * 8.0 / stream.total_time) / 1_000_000.0;
+        let line = format!(
+            "  #{:<2} {:>10} in {:.2}s - {:.2} Mbps",
+            stream.stream + 1,
+            ByteSize::b(stream.bytes_downloaded).to_string(),
+            stream.total_time,
+            mbps,
+        );
+        if stream.status_code == 200 || stream.status_code == 206 {
+            println!("{}", line.bright_black());
+        } else {
+            println!("{}", format!("{} (status {})", line, stream.status_code).red());
+        }
+    }
+}
+
+pub fn print_verify_report(reports: &[VerifyReport]) {
+    let name_width = reports.iter().map(|r| r.name.chars().count()).max().unwrap_or(0).max(6);
+    
+    println!();
+    println!(
+        "{}",
+        format!("    {:<width$}  {:>6}  {:>9}  {:>10}", "Server", "Status", "Response", "Size", width = name_width).bold()
+    );
+    
+    for report in reports {
+        let marker = match report.verdict {
+            Verdict::Ok => "✓".green(),
+            Verdict::Warning => "!".yellow(),
+            Verdict::Failed => "✗".red(),
+        };
+        let status = report.status_code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string());
+        let response = report.response_ms.map(|ms| format!("{:.0} ms", ms)).unwrap_or_else(|| "-".to_string());
+        let size = report.content_length.map(|b| ByteSize::b(b).to_string()).unwrap_or_else(|| "-".to_string());
+        
+        println!(
+            "  {} {:<width$}  {:>6}  {:>9}  {:>10}",
+            marker, report.name, status, response, size, width = name_width
+        );
+        if let Some(final_url) = report.redirects.last() {
+            println!("{}", format!("      → {} ({} redirect{})", final_url, report.redirects.len(), if report.redirects.len() == 1 { "" } else { "s" }).bright_black());
+        }
+        for issue in &report.issues {
+            let line = format!("      {}", issue);
+            match report.verdict {
+                Verdict::Failed => println!("{}", line.red()),
+                _ => println!("{}", line.yellow()),
+            }
+        }
+    }
+    
+    let count = |verdict| reports.iter().filter(|r| r.verdict == verdict).count();
+    println!();
+    println!(
+        "{}  {}  {}",
+        format!("{} ok", count(Verdict::Ok)).green(),
+        format!("{} warnings", count(Verdict::Warning)).yellow(),
+        format!("{} failed", count(Verdict::Failed)).red()
+    );
+}
+
+// Summary of a batch run: servers ranked by speed, failures last, then best, median and worst
+pub fn print_batch_report(report: &BatchReport) {
+    let ranked = report.ranked();
+    let failed: Vec<&BatchEntry> = report.entries.iter().filter(|e| e.speed_mbps.is_none()).collect();
+    let name_width = report.entries.iter().map(|e| e.name.chars().count()).max().unwrap_or(0).max(6);
+    let provider_width = report.entries.iter()
+        .map(|e| e.provider.as_deref().unwrap_or("-").chars().count())
+        .max().unwrap_or(0).max(8);
+    let speed = |mbps: f64| format!("{:.2} MB/s  ({:.2} Mbps)", mbps * 1_000_000.0 / 8.0 / 1_048_576.0, mbps);
+
+    println!();
+    println!(
+        "{}",
+        format!("  {:>3}  {:<nw$}  {:<pw$}  {:<28}  {:>10}", "#", "Server", "Provider", "Speed", "Latency", nw = name_width, pw = provider_width).bold()
+    );
+    for (i, entry) in ranked.iter().enumerate() {
+        println!(
+            "  {:>3}  {:<nw$}  {:<pw$}  {:<28}  {:>10}",
+            i + 1,
+            entry.name,
+            entry.provider.as_deref().unwrap_or("-"),
+            speed(entry.speed_mbps.unwrap_or_default()),
+            entry.latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string()),
+            nw = name_width,
+            pw = provider_width
+        );
+    }
+    for entry in &failed {
+        println!(
+            "{}",
+            format!(
+                "  {:>3}  {:<nw$}  {:<pw$}  {}",
+                "✗",
+                entry.name,
+                entry.provider.as_deref().unwrap_or("-"),
+                entry.error.as_deref().unwrap_or("failed"),
+                nw = name_width,
+                pw = provider_width
+            ).red()
+        );
+    }
+
+    println!();
+    if let (Some(best), Some(worst), Some(median)) = (report.best(), report.worst(), report.median_mbps()) {
+        println!("Best:    {}  {}", speed(best.speed_mbps.unwrap_or_default()).green(), best.name);
+        println!("Median:  {}", speed(median));
+        println!("Worst:   {}  {}", speed(worst.speed_mbps.unwrap_or_default()).yellow(), worst.name);
+    }
+    if let Some(aggregate) = report.aggregate_mbps() {
+        println!(
+            "Total:   {}  {}",
+            speed(aggregate).cyan(),
+            format!("({} at a time over {:.1}s)", report.parallel, report.wall_time).bright_black()
+        );
+    }
+    let summary = format!("{} servers tested, {} failed", report.entries.len(), failed.len());
+    if failed.is_empty() {
+        println!("{}", summary.bright_black());
+    } else {
+        println!("{}", summary.red());
+    }
+}
+
+// Past runs, oldest first, with speeds shown in the configured unit
+pub fn print_history(records: &[HistoryRecord], total: usize, speed_unit: SpeedUnit) {
+    if records.is_empty() {
+        println!("No matching test runs.");
+        return;
+    }
+
+    let speed = |mbps: Option<f64>| match mbps {
+        Some(mbps) => crate::downloader::format_speed(mbps * 1_000_000.0 / 8.0, speed_unit),
+        None => "-".to_string(),
+    };
+    let rows: Vec<(String, String)> = records.iter()
+        .map(|record| {
+            let speed = match record.mode {
+                HistoryMode::Bidir => format!("↓ {}  ↑ {}", speed(record.download_mbps), speed(record.upload_mbps)),
+                _ => speed(record.speed_mbps()),
+            };
+            let latency = record.latency_ms.map(|ms| format!("{:.1} ms", ms)).unwrap_or_else(|| "-".to_string());
+            (speed, latency)
+        })
+        .collect();
+    let name_width = records.iter().map(|r| r.server.chars().count()).max().unwrap_or(0).max(6);
+    let speed_width = rows.iter().map(|(speed, _)| speed.chars().count()).max().unwrap_or(0).max(5);
+
+    println!();
+    println!(
+        "{}",
+        format!("  {:<16}  {:<nw$}  {:<8}  {:<sw$}  {:>10}  {}", "Time", "Server", "Mode", "Speed", "Latency", "Status", nw = name_width, sw = speed_width).bold()
+    );
+    for (record, (speed, latency)) in records.iter().zip(rows) {
+        let time = record.timestamp.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+        let line = format!(
+            "  {:<16}  {:<nw$}  {:<8}  {:<sw$}  {:>10}  ",
+            time,
+            record.server,
+            record.mode.as_str(),
+            speed,
+            latency,
+            nw = name_width,
+            sw = speed_width
+        );
+        match record.status {
+            RunStatus::Ok => println!("{}{}", line, "ok".green()),
+            RunStatus::Failed => println!(
+                "{}",
+                format!("{}failed: {}", line, record.error.as_deref().unwrap_or("unknown error")).red()
+            ),
+        }
+    }
+
+    println!();
+    println!("{}", format!("Showing {} of {} runs", records.len(), total).bright_black());
+}
+
+// `speedo history stats`: per-server and per-hour tables for each direction, then trends
+pub fn print_history_stats(stats: &HistoryStats, speed_unit: SpeedUnit) {
+    let since = stats.since.with_timezone(&chrono::Local).format("%Y-%m-%d");
+    let days = stats.period_days();
+    println!();
+    println!(
+        "{}",
+        format!("Speed statistics since {} ({} days): {} runs, {} failed", since, days, stats.runs, stats.failed).bold()
+    );
+    if stats.runs == 0 {
+        println!("No test runs recorded in this period.");
+        return;
+    }
+
+    let speed = |mbps: f64| crate::downloader::format_speed(mbps * 1_000_000.0 / 8.0, speed_unit);
+    let columns = |s: &Summary| {
+        format!(
+            "{:>12}  {:>12}  {:>12}  {:>12}  {:>12}  {:>12}",
+            speed(s.median), speed(s.mean), speed(s.min), speed(s.max), speed(s.p95), speed(s.stddev)
+        )
+    };
+    let header = format!(
+        "{:>12}  {:>12}  {:>12}  {:>12}  {:>12}  {:>12}",
+        "Median", "Mean", "Min", "Max", "p95", "Std dev"
+    );
+
+    for direction in [Direction::Download, Direction::Upload] {
+        let servers: Vec<_> = stats.servers.iter().filter(|s| s.direction == direction).collect();
+        if servers.is_empty() {
+            continue;
+        }
+        let name_width = servers.iter().map(|s| s.server.chars().count()).max().unwrap_or(0).max(6);
+        let title = match direction {
+            Direction::Download => "Download",
+            Direction::Upload => "Upload",
+        };
+
+        println!();
+        println!("{}", format!("{} speed by server", title).cyan());
+        println!("{}", format!("  {:<nw$}  {:>5}  {:>6}  {}", "Server", "Runs", "Failed", header, nw = name_width).bold());
+        for server in servers {
+            let runs = format!("{:>5}  {:>6}", server.runs, failed_count(server.failed));
+            match &server.speed_mbps {
+                Some(summary) => println!("  {:<nw$}  {}  {}", server.server, runs, columns(summary), nw = name_width),
+                None => println!(
+                    "{}",
+                    format!("  {:<nw$}  {}  {:>12}", server.server, runs, "all failed", nw = name_width).red()
+                ),
+            }
+        }
+
+        let hours: Vec<_> = stats.hours.iter().filter(|h| h.direction == direction).collect();
+        if !hours.is_empty() {
+            println!();
+            println!("{}", format!("{} speed by hour of day", title).cyan());
+            println!("{}", format!("  {:<6}  {:>5}  {:>6}  {}", "Hour", "Runs", "Failed", header).bold());
+            for hour in hours {
+                println!(
+                    "  {:<6}  {:>5}  {:>6}  {}",
+                    format!("{:02}:00", hour.hour),
+                    hour.runs,
+                    failed_count(hour.failed),
+                    columns(&hour.speed_mbps)
+                );
+            }
+        }
+    }
+
+    let trends: Vec<String> = stats.servers.iter()
+        .filter_map(|server| {
+            let trend = server.trend.as_ref()?;
+            let mut changes = Vec::new();
+            // With a week or less of history, this week is the whole period
+            if days > 7 {
+                if let Some(pct) = trend.vs_period_median_pct {
+                    changes.push(describe_change(pct, &format!("your {}-day median", days)));
+                }
+            }
+            if let Some(pct) = trend.week_over_week_pct {
+                changes.push(describe_change(pct, "last week"));
+            }
+            if changes.is_empty() {
+                return None;
+            }
+            let label = match server.direction {
+                Direction::Download => server.server.clone(),
+                Direction::Upload => format!("{} (upload)", server.server),
+            };
+            Some(format!("  {}: this week {}", label.bold(), changes.join(", ")))
+        })
+        .collect();
+    if !trends.is_empty() {
+        println!();
+        println!("{}", "Trends".cyan());
+        for trend in trends {
+            println!("{}", trend);
+        }
+    }
+}
+
+fn failed_count(failed: usize) -> ColoredString {
+    if failed == 0 {
+        "-".normal()
+    } else {
+        failed.to_string().red()
+    }
+}
+
+fn describe_change(pct: f64, against: &str) -> String {
+    if pct.abs() < 1.0 {
+        format!("about the same as {}", against)
+    } else if pct < 0.0 {
+        format!("{:.0}% slower than {}", -pct, against).yellow().to_string()
+    } else {
+        format!("{:.0}% faster than {}", pct, against).green().to_string()
+    }
+}
+
+
+const CHART_WIDTH: usize = 60;
+const CHART_HEIGHT: usize = 8;
+const SPARKLINE_WIDTH: usize = 30;
+const BAR_WIDTH: usize = 20;
+
+// `speedo history --chart`: speed and latency over time, then a sparkline and median bar per
+// server, coloured by provider as in the menu
+pub fn print_history_chart(records: &[HistoryRecord], speed_unit: SpeedUnit, servers: &[ServerMetadata]) {
+    if records.is_empty() {
+        println!("No matching test runs.");
+        return;
+    }
+
+    let color_map = build_provider_color_map(servers);
+    let speed = |mbps: f64| crate::downloader::format_speed(mbps * 1_000_000.0 / 8.0, speed_unit);
+    let latency = |ms: f64| format!("{:.1} ms", ms);
+
+    for direction in [Direction::Download, Direction::Upload] {
+        let points: Vec<(&HistoryRecord, f64)> = records.iter()
+            .filter_map(|r| direction.speed(r).map(|mbps| (r, mbps)))
+            .collect();
+        let title = match direction {
+            Direction::Download => "Download speed",
+            Direction::Upload => "Upload speed",
+        };
+        print_time_chart(title, &points, &color_map, &speed);
+        print_server_trends(&points, &color_map, &speed);
+    }
+
+    let points: Vec<(&HistoryRecord, f64)> = records.iter()
+        .filter_map(|r| r.latency_ms.map(|ms| (r, ms)))
+        .collect();
+    print_time_chart("Latency", &points, &color_map, &latency);
+    print_server_trends(&points, &color_map, &latency);
+}
+
+// Column chart from the first to the last point: each column is the median of the runs in
+// its time slice, coloured by the provider of the latest of them
+fn print_time_chart(title: &str, points: &[(&HistoryRecord, f64)], color_map: &HashMap<String, Color>, label: &dyn Fn(f64) -> String) {
+    let (Some(first), Some(last)) = (points.iter().map(|(r, _)| r.timestamp).min(), points.iter().map(|(r, _)| r.timestamp).max()) else {
+        return;
+    };
+    let span = (last - first).num_seconds().max(1) as f64;
+
+    let slices = time_slices(points);
+    let colors: Vec<Color> = slices.iter()
+        .map(|slice| slice.last().map_or(Color::White, |(record, _)| get_provider_color(&record.provider, color_map)))
+        .collect();
+    let values: Vec<Option<f64>> = slices.iter()
+        .map(|slice| Summary::from_values(&slice.iter().map(|(_, v)| *v).collect::<Vec<f64>>()).map(|s| s.median))
+        .collect();
+    let max = values.iter().flatten().copied().fold(0.0, f64::max);
+
+    let labels = [label(max), label(max / 2.0), "0".to_string()];
+    let label_width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+
+    println!();
+    println!("{}", format!("{} over time", title).cyan());
+    for (i, row) in crate::chart::columns(&values, max, CHART_HEIGHT).iter().enumerate() {
+        let (label, tick) = match i {
+            0 => (labels[0].as_str(), '┤'),
+            i if i == CHART_HEIGHT / 2 => (labels[1].as_str(), '┤'),
+            _ => ("", '│'),
+        };
+        let cells: String = row.iter()
+            .zip(&colors)
+            .map(|(cell, color)| cell.to_string().color(*color).to_string())
+            .collect();
+        println!("  {:>lw$} {}{}", label, tick, cells, lw = label_width);
+    }
+    println!("  {:>lw$} └{}", labels[2], "─".repeat(CHART_WIDTH), lw = label_width);
+
+    // Times of the first and last run under the ends of the axis
+    let format = if span < 2.0 * 86400.0 { "%m-%d %H:%M" } else { "%Y-%m-%d" };
+    let start = first.with_timezone(&chrono::Local).format(format).to_string();
+    let end = last.with_timezone(&chrono::Local).format(format).to_string();
+    println!(
+        "{}",
+        format!("  {:lw$}  {}{:>rest$}", "", start, end, lw = label_width, rest = CHART_WIDTH - start.chars().count()).bright_black()
+    );
+}
+
+// Splits points into CHART_WIDTH time slices from the earliest to the latest, each in time
+// order. History is appended as runs finish, so the file is not always in timestamp order.
+fn time_slices<'a>(points: &[(&'a HistoryRecord, f64)]) -> Vec<Vec<(&'a HistoryRecord, f64)>> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by_key(|(record, _)| record.timestamp);
+    let mut slices = vec![Vec::new(); CHART_WIDTH];
+    let (Some((first, _)), Some((last, _))) = (sorted.first(), sorted.last()) else {
+        return slices;
+    };
+    let span = (last.timestamp - first.timestamp).num_seconds().max(1) as f64;
+
+    for &(record, value) in &sorted {
+        let offset = (record.timestamp - first.timestamp).num_seconds() as f64;
+        let column = ((offset / span * (CHART_WIDTH - 1) as f64).round() as usize).min(CHART_WIDTH - 1);
+        slices[column].push((record, value));
+    }
+    slices
+}
+
+// One row per server: its values as a sparkline in run order, then a bar of its median
+// against the largest median
+fn print_server_trends(points: &[(&HistoryRecord, f64)], color_map: &HashMap<String, Color>, label: &dyn Fn(f64) -> String) {
+    let mut servers: Vec<(&HistoryRecord, Vec<f64>)> = Vec::new();
+    for (record, value) in points {
+        match servers.iter_mut().find(|(latest, _)| latest.url == record.url) {
+            Some((latest, values)) => {
+                *latest = record;
+                values.push(*value);
+            }
+            None => servers.push((record, vec![*value])),
+        }
+    }
+    if servers.is_empty() {
+        return;
+    }
+
+    let mut rows: Vec<(&HistoryRecord, Vec<f64>, f64)> = servers.into_iter()
+        .filter_map(|(record, values)| Summary::from_values(&values).map(|s| (record, values, s.median)))
+        .collect();
+    rows.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    let max = rows.iter().map(|(_, _, median)| *median).fold(0.0, f64::max);
+    let name_width = rows.iter().map(|(r, _, _)| r.server.chars().count()).max().unwrap_or(0).max(6);
+
+    println!();
+    println!(
+        "{}",
+        format!("  {:<nw$}  {:<sw$}  {}", "Server", "Trend", "Median", nw = name_width, sw = SPARKLINE_WIDTH).bold()
+    );
+    for (record, values, median) in rows {
+        let color = get_provider_color(&record.provider, color_map);
+        println!(
+            "  {}  {}  {}  {}",
+            format!("{:<nw$}", record.server, nw = name_width).color(color),
+            format!("{:<sw$}", crate::chart::sparkline(&values, SPARKLINE_WIDTH), sw = SPARKLINE_WIDTH).color(color),
+            crate::chart::bar(median, max, BAR_WIDTH).color(color),
+            label(median)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn record(minutes: i64) -> HistoryRecord {
+        let mut record = HistoryRecord::new("Test", "http://example.com/file", None, HistoryMode::Download, SpeedUnit::BitsMetric);
+        record.timestamp = Utc.with_ymd_and_hms(2025, 11, 1, 12, 0, 0).unwrap() + Duration::minutes(minutes);
+        record
+    }
+
+    #[test]
+    fn time_slices_sort_records_out_of_order() {
+        let records = [record(0), record(60), record(30), record(90)];
+        let points: Vec<(&HistoryRecord, f64)> = records.iter().zip([1.0, 2.0, 3.0, 4.0]).collect();
+
+        let slices = time_slices(&points);
+        assert_eq!(slices.len(), CHART_WIDTH);
+        assert_eq!(slices[0].iter().map(|(_, v)| *v).collect::<Vec<_>>(), [1.0]);
+        assert_eq!(slices[CHART_WIDTH - 1].iter().map(|(_, v)| *v).collect::<Vec<_>>(), [4.0]);
+        let order: Vec<f64> = slices.iter().flatten().map(|(_, v)| *v).collect();
+        assert_eq!(order, [1.0, 3.0, 2.0, 4.0]);
+    }
+
+    #[test]
+    fn time_slices_of_one_point() {
+        let records = [record(0)];
+        let points: Vec<(&HistoryRecord, f64)> = records.iter().map(|r| (r, 5.0)).collect();
+        assert_eq!(time_slices(&points).iter().flatten().count(), 1);
+    }
+}
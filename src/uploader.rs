@@ -0,0 +1,144 @@
+// HTTP upload speed testing.
+// Streams generated in-memory random data to upload-capable servers with POST or PUT,
+// reporting connection time, bytes sent, and upload speed.
+
+use bytes::Bytes;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::config::SpeedUnit;
+use crate::downloader::{format_speed, Progress};
+use crate::timing::{timed_client, PhaseTimings};
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub struct UploadOptions {
+    // Bytes to send; with a duration this is an upper bound
+    pub size: u64,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadResult {
+    pub status_code: u16,
+    // Seconds from start until the connection was ready (DNS + TCP + TLS)
+    pub connect_time: f64,
+    // Seconds from start until the response headers arrived, after the body was sent
+    pub ttfb: f64,
+    pub total_time: f64,
+    pub bytes_uploaded: u64,
+    pub timings: PhaseTimings,
+    // Whether the upload was cut off by the duration budget
+    pub time_limited: bool,
+}
+
+impl UploadResult {
+    pub fn bytes_per_second(&self) -> f64 {
+        // Exclude connection setup so only the body transfer counts
+        let transfer = self.timings.transfer;
+        if transfer > 0.0 {
+            self.bytes_uploaded as f64 / transfer
+        } else if self.total_time > 0.0 {
+            self.bytes_uploaded as f64 / self.total_time
+        } else {
+            0.0
+        }
+    }
+}
+
+// Fills a buffer with xorshift output so the payload is not compressible
+fn random_chunk() -> Bytes {
+    let seed = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0);
+    let mut state = seed | 1;
+    let mut buf = Vec::with_capacity(CHUNK_SIZE);
+    while buf.len() < CHUNK_SIZE {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        buf.extend_from_slice(&state.to_le_bytes());
+    }
+    buf.truncate(CHUNK_SIZE);
+    Bytes::from(buf)
+}
+
+pub async fn upload_data(
+    url: &str,
+    method: &str,
+    user_agent: &str,
+    speed_unit: SpeedUnit,
+    options: UploadOptions,
+    progress: impl Into<Progress>,
+) -> Result<UploadResult, Box<dyn std::error::Error>> {
+    let UploadOptions { size, duration } = options;
+    let (client, recorder) = timed_client(url, user_agent)?;
+    let method = reqwest::Method::from_bytes(method.to_uppercase().as_bytes())?;
+
+    let pb = progress.into().bar(Some(size));
+
+    let start = Instant::now();
+    let deadline = duration.map(|d| start + d);
+    let sent = Arc::new(AtomicU64::new(0));
+
+    // The body is generated lazily from one shared random chunk, so progress
+    // follows what the connection actually consumed
+    let chunk = random_chunk();
+    let stream_pb = pb.clone();
+    let stream_sent = sent.clone();
+    let mut last_update = Instant::now();
+    let mut last_sent = 0u64;
+    let body_stream = futures_util::stream::poll_fn(move |_| {
+        use std::task::Poll;
+
+        let total = stream_sent.load(Ordering::Relaxed);
+        let now = Instant::now();
+        if total >= size || deadline.is_some_and(|d| now >= d) {
+            return Poll::Ready(None);
+        }
+
+        if now.duration_since(last_update).as_millis() >= 100 {
+            let speed = (total - last_sent) as f64 / now.duration_since(last_update).as_secs_f64();
+            stream_pb.set_message(format_speed(speed, speed_unit));
+            last_update = now;
+            last_sent = total;
+        }
+
+        let len = (size - total).min(chunk.len() as u64) as usize;
+        stream_sent.fetch_add(len as u64, Ordering::Relaxed);
+        stream_pb.set_position(total + len as u64);
+        Poll::Ready(Some(Ok::<Bytes, std::io::Error>(chunk.slice(..len))))
+    });
+
+    let mut request = client
+        .request(method, url)
+        .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+        .body(reqwest::Body::wrap_stream(body_stream));
+    // A known length lets servers that reject chunked uploads accept the request
+    if duration.is_none() {
+        request = request.header(reqwest::header::CONTENT_LENGTH, size);
+    }
+
+    let response = request.send().await?;
+    let headers_at = Instant::now();
+    let status_code = response.status().as_u16();
+    // Drain the (usually tiny) reply so the connection closes cleanly
+    response.bytes().await.ok();
+    let end = Instant::now();
+
+    pb.finish_and_clear();
+
+    let ttfb = headers_at.duration_since(start).as_secs_f64();
+    let connect_time = recorder.connect_time(start).unwrap_or(ttfb);
+    let bytes_uploaded = sent.load(Ordering::Relaxed);
+
+    Ok(UploadResult {
+        status_code,
+        connect_time,
+        ttfb,
+        total_time: end.duration_since(start).as_secs_f64(),
+        bytes_uploaded,
+        timings: recorder.upload_timings(start, headers_at),
+        time_limited: bytes_uploaded < size,
+    })
+}
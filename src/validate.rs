@@ -0,0 +1,249 @@
+// Server list linter for `speedo servers validate`.
+// Checks a servers.json (or TOML fragment) before it is published: structure, duplicate URLs,
+// required metadata, coordinates, and optionally each file's size against a live HEAD request.
+
+use crate::servers::{ServerMetadata, SERVER_LIST_SCHEMA};
+use crate::verify;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const LIST_FIELDS: &[&str] = &["schema", "version", "updated", "servers"];
+const SERVER_FIELDS: &[&str] = &[
+    "name", "url", "provider", "location", "region", "lat", "lon",
+    "file_size", "enabled", "upload_url", "upload_method",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Finding {
+    pub severity: Severity,
+    // Position in the `servers` array; None for problems with the list itself
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub file: String,
+    pub servers: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub findings: Vec<Finding>,
+}
+
+struct Findings(Vec<Finding>);
+
+impl Findings {
+    fn error(&mut self, index: Option<usize>, name: Option<&str>, message: String) {
+        self.push(Severity::Error, index, name, message);
+    }
+
+    fn warn(&mut self, index: Option<usize>, name: Option<&str>, message: String) {
+        self.push(Severity::Warning, index, name, message);
+    }
+
+    fn push(&mut self, severity: Severity, index: Option<usize>, name: Option<&str>, message: String) {
+        self.0.push(Finding { severity, index, name: name.map(str::to_string), message });
+    }
+}
+
+// Lints a list file. With `live` set, every enabled entry is probed and anything
+// --verify-servers would flag, including a file_size mismatch, counts as an error here.
+pub async fn validate_file(path: &Path, user_agent: &str, live: bool, show_progress: bool) -> Result<ValidationReport, Box<dyn std::error::Error>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut findings = Findings(Vec::new());
+
+    let toml = crate::sources::is_toml(&path.to_string_lossy());
+    let value: Result<serde_json::Value, String> = if toml {
+        toml::from_str(&contents).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_str(&contents).map_err(|e| e.to_string())
+    };
+    let servers = match value {
+        Ok(value) => check_structure(value, &mut findings),
+        Err(e) => {
+            findings.error(None, None, format!("not valid {}: {}", if toml { "TOML" } else { "JSON" }, e));
+            Vec::new()
+        }
+    };
+
+    check_duplicates(&servers, &mut findings);
+
+    if live {
+        let probed: Vec<(usize, ServerMetadata)> = servers.iter()
+            .filter(|(_, server)| server.enabled)
+            .cloned()
+            .collect();
+        let list: Vec<ServerMetadata> = probed.iter().map(|(_, server)| server.clone()).collect();
+        let reports = verify::verify_servers(&list, user_agent, show_progress).await?;
+        for ((index, server), report) in probed.iter().zip(reports) {
+            for issue in report.issues {
+                findings.error(Some(*index), Some(&server.name), issue);
+            }
+        }
+    }
+
+    let mut findings = findings.0;
+    findings.sort_by_key(|f| f.index.map_or(0, |i| i + 1));
+    Ok(ValidationReport {
+        file: path.display().to_string(),
+        servers: servers.len(),
+        errors: findings.iter().filter(|f| f.severity == Severity::Error).count(),
+        warnings: findings.iter().filter(|f| f.severity == Severity::Warning).count(),
+        findings,
+    })
+}
+
+// Checks the list's own fields and every entry. Returns the entries that parsed, with their
+// position in the file.
+fn check_structure(value: serde_json::Value, findings: &mut Findings) -> Vec<(usize, ServerMetadata)> {
+    let serde_json::Value::Object(fields) = value else {
+        findings.error(None, None, "the list must be an object with a `servers` array".to_string());
+        return Vec::new();
+    };
+
+    for key in fields.keys().filter(|k| !LIST_FIELDS.contains(&k.as_str())) {
+        findings.warn(None, None, format!("unknown field `{}`", key));
+    }
+
+    match fields.get("schema") {
+        None => {}
+        Some(schema) => match schema.as_u64() {
+            Some(schema) if u32::try_from(schema).map_or(true, |s| s > SERVER_LIST_SCHEMA) => findings.error(
+                None,
+                None,
+                format!("schema {} is newer than this speedo understands ({})", schema, SERVER_LIST_SCHEMA),
+            ),
+            Some(0) | None => findings.error(None, None, format!("`schema` must be a positive integer, got {}", schema)),
+            Some(_) => {}
+        },
+    }
+    if let Some(version) = fields.get("version") {
+        if !version.is_string() {
+            findings.error(None, None, "`version` must be a string".to_string());
+        }
+    }
+    if let Some(updated) = fields.get("updated") {
+        if serde_json::from_value::<chrono::DateTime<chrono::Utc>>(updated.clone()).is_err() {
+            findings.error(None, None, format!("`updated` is not an RFC 3339 timestamp: {}", updated));
+        }
+    }
+
+    let entries = match fields.get("servers") {
+        Some(serde_json::Value::Array(entries)) => entries,
+        Some(_) => {
+            findings.error(None, None, "`servers` must be an array".to_string());
+            return Vec::new();
+        }
+        None => {
+            findings.error(None, None, "missing `servers` array".to_string());
+            return Vec::new();
+        }
+    };
+
+    let mut servers = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        let name = entry.get("name").and_then(|n| n.as_str());
+        if let Some(entry_fields) = entry.as_object() {
+            for key in entry_fields.keys().filter(|k| !SERVER_FIELDS.contains(&k.as_str())) {
+                findings.warn(Some(index), name, format!("unknown field `{}`", key));
+            }
+        }
+        match serde_json::from_value::<ServerMetadata>(entry.clone()) {
+            Ok(server) => {
+                check_server(index, &server, findings);
+                servers.push((index, server));
+            }
+            Err(e) => findings.error(Some(index), name, format!("invalid entry: {}", e)),
+        }
+    }
+    servers
+}
+
+fn check_server(index: usize, server: &ServerMetadata, findings: &mut Findings) {
+    let name = Some(server.name.as_str());
+
+    if server.name.trim().is_empty() {
+        findings.error(Some(index), name, "`name` is empty".to_string());
+    }
+    check_url(index, server, "url", &server.url, findings);
+    if let Some(upload_url) = &server.upload_url {
+        check_url(index, server, "upload_url", upload_url, findings);
+    }
+    if let Some(method) = &server.upload_method {
+        if !method.eq_ignore_ascii_case("POST") && !method.eq_ignore_ascii_case("PUT") {
+            findings.error(Some(index), name, format!("`upload_method` must be POST or PUT, got {}", method));
+        }
+    }
+
+    for (field, value) in [("provider", &server.provider), ("region", &server.region)] {
+        if value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+            findings.error(Some(index), name, format!("missing `{}`", field));
+        }
+    }
+    if server.location.is_none() {
+        findings.warn(Some(index), name, "missing `location`".to_string());
+    }
+
+    match (server.lat, server.lon) {
+        (Some(lat), Some(lon)) => {
+            if !(-90.0..=90.0).contains(&lat) {
+                findings.error(Some(index), name, format!("`lat` {} is outside -90..90", lat));
+            }
+            if !(-180.0..=180.0).contains(&lon) {
+                findings.error(Some(index), name, format!("`lon` {} is outside -180..180", lon));
+            }
+        }
+        (Some(_), None) => findings.error(Some(index), name, "`lat` without `lon`".to_string()),
+        (None, Some(_)) => findings.error(Some(index), name, "`lon` without `lat`".to_string()),
+        (None, None) => {}
+    }
+
+    match server.file_size {
+        Some(0) => findings.error(Some(index), name, "`file_size` is 0".to_string()),
+        None => findings.warn(Some(index), name, "no `file_size`, so the download size cannot be checked".to_string()),
+        Some(_) => {}
+    }
+}
+
+fn check_url(index: usize, server: &ServerMetadata, field: &str, url: &str, findings: &mut Findings) {
+    let name = Some(server.name.as_str());
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "https" => {}
+        Ok(parsed) if parsed.scheme() == "http" => {
+            findings.warn(Some(index), name, format!("`{}` uses plain HTTP", field));
+        }
+        Ok(parsed) => findings.error(Some(index), name, format!("`{}` has unsupported scheme {}", field, parsed.scheme())),
+        Err(e) => findings.error(Some(index), name, format!("`{}` is not a valid URL: {}", field, e)),
+    }
+}
+
+fn check_duplicates(servers: &[(usize, ServerMetadata)], findings: &mut Findings) {
+    let mut urls: HashMap<&str, usize> = HashMap::new();
+    let mut names: HashMap<String, usize> = HashMap::new();
+
+    for (index, server) in servers {
+        if let Some(&first) = urls.get(server.url.as_str()) {
+            findings.error(Some(*index), Some(&server.name), format!("duplicate URL, already used by servers[{}]", first));
+        } else {
+            urls.insert(&server.url, *index);
+        }
+        let key = server.name.to_lowercase();
+        if let Some(&first) = names.get(&key) {
+            findings.warn(Some(*index), Some(&server.name), format!("duplicate name, already used by servers[{}]", first));
+        } else {
+            names.insert(key, *index);
+        }
+    }
+}
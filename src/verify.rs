@@ -0,0 +1,256 @@
+// Server list verification for --verify-servers.
+// Sends a HEAD (or a one-byte ranged GET when HEAD is not supported) to every server and checks
+// the status, the advertised size against the list's file_size, and where redirects lead.
+
+use crate::servers::{HealthSample, ServerMetadata};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, LOCATION, RANGE};
+use reqwest::{Client, Method, StatusCode, Url};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+const VERIFY_CONCURRENCY: usize = 8;
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_REDIRECTS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Ok,
+    Warning,
+    Failed,
+}
+
+impl Verdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Ok => "ok",
+            Verdict::Warning => "warning",
+            Verdict::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VerifyReport {
+    pub name: String,
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    pub verdict: Verdict,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status_code: Option<u16>,
+    // "HEAD", or "GET" when the server needed the ranged fallback
+    pub method: &'static str,
+    // Milliseconds until the first response headers arrived
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_length: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected_size: Option<u64>,
+    // Every Location followed, in order; the last one is where the file was found
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub redirects: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub issues: Vec<String>,
+}
+
+impl VerifyReport {
+    fn new(server: &ServerMetadata) -> Self {
+        Self {
+            name: server.name.clone(),
+            url: server.url.clone(),
+            provider: server.provider.clone(),
+            verdict: Verdict::Ok,
+            reachable: false,
+            status_code: None,
+            method: "HEAD",
+            response_ms: None,
+            content_length: None,
+            expected_size: server.file_size,
+            redirects: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    fn fail(&mut self, issue: String) {
+        self.verdict = Verdict::Failed;
+        self.issues.push(issue);
+    }
+
+    fn warn(&mut self, issue: String) {
+        if self.verdict == Verdict::Ok {
+            self.verdict = Verdict::Warning;
+        }
+        self.issues.push(issue);
+    }
+
+    // A probe only shows the server answers, so it counts towards success rate and latency
+    // but leaves the measured download speed alone
+    pub fn health_sample(&self) -> HealthSample {
+        if self.verdict == Verdict::Failed {
+            return HealthSample::failure();
+        }
+        HealthSample {
+            success: true,
+            speed_mbps: None,
+            latency_ms: self.response_ms,
+        }
+    }
+}
+
+// Probes every server with bounded concurrency. Reports come back in list order.
+pub async fn verify_servers(servers: &[ServerMetadata], user_agent: &str, show_progress: bool) -> Result<Vec<VerifyReport>, Box<dyn std::error::Error>> {
+    use futures_util::StreamExt;
+
+    // Redirects are followed by hand so each hop can be reported
+    let client = Client::builder()
+        .user_agent(user_agent)
+        .redirect(reqwest::redirect::Policy::none())
+        .timeout(VERIFY_TIMEOUT)
+        .build()?;
+
+    let pb = if show_progress {
+        let pb = ProgressBar::new(servers.len() as u64);
+        pb.set_style(ProgressStyle::default_bar().template("{bar:40.cyan/blue} {pos}/{len} {msg}")?);
+        pb.set_message("servers checked");
+        Some(pb)
+    } else {
+        None
+    };
+
+    let mut reports: Vec<(usize, VerifyReport)> = futures_util::stream::iter(servers.iter().enumerate())
+        .map(|(i, server)| {
+            let client = &client;
+            let pb = &pb;
+            async move {
+                let report = verify_server(client, server).await;
+                if let Some(pb) = pb {
+                    pb.inc(1);
+                }
+                (i, report)
+            }
+        })
+        .buffer_unordered(VERIFY_CONCURRENCY)
+        .collect()
+        .await;
+
+    if let Some(pb) = pb {
+        pb.finish_and_clear();
+    }
+
+    reports.sort_by_key(|(i, _)| *i);
+    Ok(reports.into_iter().map(|(_, report)| report).collect())
+}
+
+async fn verify_server(client: &Client, server: &ServerMetadata) -> VerifyReport {
+    let mut report = VerifyReport::new(server);
+
+    let mut url = match Url::parse(&server.url) {
+        Ok(url) => url,
+        Err(e) => {
+            report.fail(format!("invalid URL: {}", e));
+            return report;
+        }
+    };
+
+    let start = Instant::now();
+    let mut response = loop {
+        let response = match client.head(url.clone()).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                report.fail(describe_error(&e));
+                return report;
+            }
+        };
+        report.reachable = true;
+        if report.response_ms.is_none() {
+            report.response_ms = Some(start.elapsed().as_secs_f64() * 1000.0);
+        }
+
+        let Some(next) = redirect_target(&response, &url) else {
+            break response;
+        };
+        if report.redirects.len() == MAX_REDIRECTS {
+            report.fail(format!("more than {} redirects", MAX_REDIRECTS));
+            return report;
+        }
+        if url.scheme() == "https" && next.scheme() == "http" {
+            report.warn(format!("redirect downgrades to plain HTTP: {}", next));
+        }
+        report.redirects.push(next.to_string());
+        url = next;
+    };
+
+    // Some servers reject HEAD or leave out the length; one byte of the file tells us the size
+    let mut content_length = header_u64(&response, CONTENT_LENGTH);
+    if matches!(response.status(), StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED)
+        || (response.status().is_success() && content_length.is_none())
+    {
+        report.method = "GET";
+        match client.request(Method::GET, url.clone()).header(RANGE, "bytes=0-0").send().await {
+            Ok(ranged) => {
+                content_length = if ranged.status() == StatusCode::PARTIAL_CONTENT {
+                    content_range_total(&ranged)
+                } else {
+                    header_u64(&ranged, CONTENT_LENGTH)
+                };
+                response = ranged;
+            }
+            Err(e) => {
+                report.fail(describe_error(&e));
+                return report;
+            }
+        }
+    }
+
+    let status = response.status();
+    report.status_code = Some(status.as_u16());
+    report.content_length = content_length;
+
+    if !status.is_success() {
+        report.fail(format!("HTTP {}", status));
+        return report;
+    }
+
+    match (report.expected_size, content_length) {
+        (Some(expected), Some(actual)) if expected != actual => {
+            report.warn(format!("size is {} bytes, list says {}", actual, expected));
+        }
+        (Some(_), None) => report.warn("server does not report the file size".to_string()),
+        _ => {}
+    }
+
+    report
+}
+
+fn redirect_target(response: &reqwest::Response, base: &Url) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    base.join(location).ok()
+}
+
+// Read from the header itself: reqwest's content_length() is the body size, which is 0 for HEAD
+fn header_u64(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<u64> {
+    response.headers().get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+// "bytes 0-0/104857600" -> 104857600
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    value.rsplit_once('/')?.1.trim().parse().ok()
+}
+
+fn describe_error(e: &reqwest::Error) -> String {
+    if e.is_timeout() {
+        format!("timed out after {}s", VERIFY_TIMEOUT.as_secs())
+    } else if e.is_connect() {
+        "connection failed".to_string()
+    } else {
+        e.to_string()
+    }
+}